@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::VariableConfig,
+    evaluation::{Evaluation, RunOutcome},
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TieBreak {
+    // lowest combination index
+    #[default]
+    First,
+    // parameters closest to the middle of their ranges
+    Central,
+    // parameters with the fewest significant digits
+    Simplest,
+}
+
+// every successful evaluation within `epsilon` of the best profit, in index order
+pub fn plateau(evaluations: &[Evaluation], epsilon: f64) -> Vec<&Evaluation> {
+    let Some(max_profit) = evaluations
+        .iter()
+        .filter_map(|e| e.profit)
+        .max_by(|a, b| a.total_cmp(b))
+    else {
+        return Vec::new();
+    };
+
+    let mut plateau = evaluations
+        .iter()
+        .filter(|e| e.profit.is_some_and(|p| p >= max_profit - epsilon))
+        .collect::<Vec<_>>();
+
+    plateau.sort_by_key(|e| e.index);
+    plateau
+}
+
+pub fn break_tie<'a>(
+    plateau: &[&'a Evaluation],
+    vars: &[VariableConfig],
+    rule: TieBreak,
+) -> Option<&'a Evaluation> {
+    // plateau is in index order and min_by keeps the first minimum, so equal
+    // scores always fall back to the lowest index
+    plateau
+        .iter()
+        .copied()
+        .min_by(|a, b| tie_score(a, vars, rule).total_cmp(&tie_score(b, vars, rule)))
+}
+
+fn tie_score(evaluation: &Evaluation, vars: &[VariableConfig], rule: TieBreak) -> f64 {
+    match rule {
+        TieBreak::First => evaluation.index as f64,
+        TieBreak::Central => vars
+            .iter()
+            .filter_map(|var| {
+                let value = evaluation.parameters.get(&var.name)?;
+                let mid = (var.start + var.end) / 2.;
+                let width = (var.end - var.start).abs();
+
+                Some(if width == 0. {
+                    0.
+                } else {
+                    ((value - mid) / width).powi(2)
+                })
+            })
+            .sum(),
+        TieBreak::Simplest => evaluation
+            .parameters
+            .values()
+            .map(|value| significant_digits(*value) as f64)
+            .sum(),
+    }
+}
+
+fn significant_digits(value: f64) -> usize {
+    format!("{}", value.abs())
+        .chars()
+        .filter(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .trim_matches('0')
+        .len()
+}
+
+// parameters whose value never changes the profit while every other parameter
+// is held fixed, which almost always means they aren't wired into the strategy
+pub fn dead_parameters(evaluations: &[Evaluation], vars: &[VariableConfig]) -> Vec<String> {
+    vars.iter()
+        .filter(|var| has_no_effect(evaluations, &var.name))
+        .map(|var| var.name.clone())
+        .collect()
+}
+
+fn has_no_effect(evaluations: &[Evaluation], name: &str) -> bool {
+    // profits keyed by the values of every other parameter, then by this one
+    let mut slices: HashMap<Vec<(&String, u64)>, HashMap<u64, f64>> = HashMap::new();
+
+    for evaluation in evaluations {
+        let (Some(profit), Some(value)) = (evaluation.profit, evaluation.parameters.get(name))
+        else {
+            continue;
+        };
+
+        let others = evaluation
+            .parameters
+            .iter()
+            .filter(|(other, _)| *other != name)
+            .map(|(other, v)| (other, v.to_bits()))
+            .collect::<Vec<_>>();
+
+        slices
+            .entry(others)
+            .or_default()
+            .insert(value.to_bits(), profit);
+    }
+
+    let mut varied = false;
+
+    for profits in slices.values().filter(|profits| profits.len() > 1) {
+        varied = true;
+
+        let mut profits = profits.values();
+        let first = profits.next().unwrap();
+
+        if profits.any(|profit| profit != first) {
+            return false;
+        }
+    }
+
+    varied
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterSummary {
+    pub name: String,
+    pub best: f64,
+    pub min: f64,
+    pub max: f64,
+    pub count: usize,
+}
+
+impl ParameterSummary {
+    // true when the best value is the smallest or largest one that was tried,
+    // meaning the optimum may lie outside the searched range
+    pub fn at_boundary(&self) -> bool {
+        self.count > 1 && (self.best == self.min || self.best == self.max)
+    }
+}
+
+pub fn parameter_summaries(
+    best: &Evaluation,
+    evaluations: &[Evaluation],
+    vars: &[VariableConfig],
+) -> Vec<ParameterSummary> {
+    vars.iter()
+        .filter_map(|var| {
+            let best_value = *best.parameters.get(&var.name)?;
+
+            let mut values = evaluations
+                .iter()
+                .filter_map(|e| e.parameters.get(&var.name).copied())
+                .collect::<Vec<_>>();
+            values.sort_by(|a, b| a.total_cmp(b));
+            values.dedup();
+
+            Some(ParameterSummary {
+                name: var.name.clone(),
+                best: best_value,
+                min: *values.first()?,
+                max: *values.last()?,
+                count: values.len(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeoutCluster {
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    pub swept_min: f64,
+    pub swept_max: f64,
+}
+
+// parameters whose timed out combinations only cover part of the values that
+// were swept, which points at a slow region rather than runs that hang at random
+pub fn timeout_clusters(
+    evaluations: &[Evaluation],
+    vars: &[VariableConfig],
+) -> Vec<TimeoutCluster> {
+    vars.iter()
+        .filter_map(|var| {
+            let (swept_min, swept_max) = value_range(evaluations.iter(), &var.name)?;
+            let (min, max) = value_range(
+                evaluations
+                    .iter()
+                    .filter(|e| matches!(e.outcome, RunOutcome::Timeout | RunOutcome::SoftTimeout)),
+                &var.name,
+            )?;
+
+            (min > swept_min || max < swept_max).then(|| TimeoutCluster {
+                name: var.name.clone(),
+                min,
+                max,
+                swept_min,
+                swept_max,
+            })
+        })
+        .collect()
+}
+
+fn value_range<'a>(
+    evaluations: impl Iterator<Item = &'a Evaluation>,
+    name: &str,
+) -> Option<(f64, f64)> {
+    evaluations
+        .filter_map(|e| e.parameters.get(name).copied())
+        .fold(None, |range, v| match range {
+            None => Some((v, v)),
+            Some((min, max)) => Some((f64::min(min, v), f64::max(max, v))),
+        })
+}
+
+// how much of a slice's profit range the best has to drop by on both sides
+// before it counts as a spike rather than the top of a slope
+const SPIKE_DROP: f64 = 0.5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharpPeak {
+    pub name: String,
+    pub value: f64,
+    pub left_drop: f64,
+    pub right_drop: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderEnd {
+    First,
+    Last,
+}
+
+// whether the best was the first or last combination run. the ends of the
+// order are corners of the grid, so the optimum may well lie beyond them
+pub fn order_end(best: &Evaluation, evaluations: &[Evaluation]) -> Option<OrderEnd> {
+    let first = evaluations.iter().map(|e| e.index).min()?;
+    let last = evaluations.iter().map(|e| e.index).max()?;
+
+    match best.index {
+        _ if first == last => None,
+        index if index == first => Some(OrderEnd::First),
+        index if index == last => Some(OrderEnd::Last),
+        _ => None,
+    }
+}
+
+// parameters where the best sits on a narrow spike in the profit, with every
+// other parameter held at the best's values. a spike that steep between
+// neighbouring grid points is more likely noise than an edge
+pub fn sharp_peaks(
+    best: &Evaluation,
+    evaluations: &[Evaluation],
+    vars: &[VariableConfig],
+) -> Vec<SharpPeak> {
+    vars.iter()
+        .filter_map(|var| {
+            let value = *best.parameters.get(&var.name)?;
+            let best_profit = best.profit?;
+
+            let mut slice = evaluations
+                .iter()
+                .filter(|e| {
+                    // derived constants follow the variables, so only those are compared
+                    vars.iter()
+                        .filter(|other| other.name != var.name)
+                        .all(|other| {
+                            e.parameters.get(&other.name) == best.parameters.get(&other.name)
+                        })
+                })
+                .filter_map(|e| Some((*e.parameters.get(&var.name)?, e.profit?)))
+                .collect::<Vec<_>>();
+            slice.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            // a peak at the boundary is already flagged, and has only one side
+            let position = slice.iter().position(|(v, _)| *v == value)?;
+            let (_, left) = *slice.get(position.checked_sub(1)?)?;
+            let (_, right) = *slice.get(position + 1)?;
+
+            let lowest = slice
+                .iter()
+                .map(|(_, profit)| *profit)
+                .fold(best_profit, f64::min);
+            let threshold = SPIKE_DROP * (best_profit - lowest);
+
+            let left_drop = best_profit - left;
+            let right_drop = best_profit - right;
+
+            (threshold > 0. && left_drop > threshold && right_drop > threshold).then(|| SharpPeak {
+                name: var.name.clone(),
+                value,
+                left_drop,
+                right_drop,
+            })
+        })
+        .collect()
+}
+
+// how many equal bins each variable's range is cut into for its coverage
+pub const COVERAGE_BINS: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coverage {
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    // samples per bin, from the variable's start to its end
+    pub histogram: Vec<usize>,
+}
+
+impl Coverage {
+    pub fn empty_bins(&self) -> usize {
+        self.histogram.iter().filter(|count| **count == 0).count()
+    }
+}
+
+// where sampled values fell in each variable's range, for judging whether a
+// sampled strategy took enough samples
+pub fn coverage(evaluations: &[Evaluation], vars: &[VariableConfig]) -> Vec<Coverage> {
+    vars.iter()
+        .filter_map(|var| {
+            let (min, max) = value_range(evaluations.iter(), &var.name)?;
+            let mut histogram = vec![0; COVERAGE_BINS];
+
+            for e in evaluations {
+                if let Some(fraction) = e.parameters.get(&var.name).map(|v| scaled(*v, var)) {
+                    let bin = (fraction * COVERAGE_BINS as f64) as usize;
+                    histogram[bin.min(COVERAGE_BINS - 1)] += 1;
+                }
+            }
+
+            Some(Coverage {
+                name: var.name.clone(),
+                min,
+                max,
+                histogram,
+            })
+        })
+        .collect()
+}
+
+// the smallest distance between two samples, with every variable scaled to
+// 0..1 so none dominates by having the widest range
+pub fn min_distance(evaluations: &[Evaluation], vars: &[VariableConfig]) -> Option<f64> {
+    let points = evaluations
+        .iter()
+        .map(|e| {
+            vars.iter()
+                .map(|var| e.parameters.get(&var.name).map_or(0., |v| scaled(*v, var)))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    points
+        .iter()
+        .enumerate()
+        .flat_map(|(i, a)| points[i + 1..].iter().map(move |b| (a, b)))
+        .map(|(a, b)| {
+            a.iter()
+                .zip(b)
+                .map(|(a, b)| (a - b).powi(2))
+                .sum::<f64>()
+                .sqrt()
+        })
+        .min_by(|a, b| a.total_cmp(b))
+}
+
+fn scaled(value: f64, var: &VariableConfig) -> f64 {
+    let width = var.end - var.start;
+
+    if width == 0. {
+        0.
+    } else {
+        ((value - var.start) / width).clamp(0., 1.)
+    }
+}
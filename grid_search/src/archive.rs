@@ -0,0 +1,93 @@
+use std::{
+    ffi::OsStr,
+    fs::{self, File},
+    path::Path,
+};
+
+use flate2::{Compression, write::GzEncoder};
+use tar::Builder;
+
+use crate::{config::Config, results_csv::results_config_path};
+
+// each bucket's per-run output, left out of an archive unless asked for
+const RUN_OUTPUT_DIRS: [&str; 2] = ["logs", "scripts"];
+
+// bundles a finished sweep into one .tar.gz: logs_dir under its own name,
+// with the manifests, config copy and anything else the sweep left there,
+// and the results csv and state file at the top. returns how many files went in
+pub fn archive_sweep(
+    cfg: &Config,
+    out: &Path,
+    include_logs: bool,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let logs_dir = Path::new(&cfg.logs_dir);
+    if !logs_dir.is_dir() {
+        return Err(format!("{} isn't a directory, has the sweep run?", cfg.logs_dir).into());
+    }
+
+    let file =
+        File::create(out).map_err(|why| format!("couldn't create {}: {}", out.display(), why))?;
+    let mut builder = Builder::new(GzEncoder::new(file, Compression::default()));
+
+    let name = Path::new(logs_dir.file_name().unwrap_or(OsStr::new("logs_dir")));
+    let mut files = append_dir(&mut builder, logs_dir, name, include_logs)?;
+
+    // the results csv's config too, so an unpacked csv can still be merged
+    let results_config = cfg
+        .results_csv
+        .as_deref()
+        .map(|path| results_config_path(path).to_string_lossy().into_owned());
+
+    for path in [&cfg.results_csv, &results_config, &cfg.state_file]
+        .into_iter()
+        .flatten()
+    {
+        let path = Path::new(path);
+
+        // the state file can already be in logs_dir, e.g. for a batch or a slice
+        if path.starts_with(logs_dir) || !path.is_file() {
+            continue;
+        }
+
+        builder.append_path_with_name(path, path.file_name().unwrap())?;
+        files += 1;
+    }
+
+    builder.into_inner()?.finish()?;
+
+    Ok(files)
+}
+
+fn append_dir(
+    builder: &mut Builder<GzEncoder<File>>,
+    dir: &Path,
+    name: &Path,
+    include_logs: bool,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    // the same sweep archives to the same listing
+    entries.sort();
+
+    let mut files = 0;
+
+    for path in entries {
+        let entry_name = name.join(path.file_name().unwrap());
+
+        if path.is_dir() {
+            let run_output = path
+                .file_name()
+                .is_some_and(|dir| RUN_OUTPUT_DIRS.iter().any(|d| dir == *d));
+
+            if !run_output || include_logs {
+                files += append_dir(builder, &path, &entry_name, include_logs)?;
+            }
+        } else {
+            builder.append_path_with_name(&path, &entry_name)?;
+            files += 1;
+        }
+    }
+
+    Ok(files)
+}
@@ -1,27 +1,219 @@
-use std::u8;
+use clap::{CommandFactory, Parser, Subcommand, error::ErrorKind};
 
-use clap::Parser;
-use rayon;
+use crate::config::{CsvQuote, Progress, Slice};
 
 #[derive(Parser, Debug)]
 pub struct Options {
-    #[arg(short, long, default_value_t = String::from(""))]
-    pub config: String,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[arg(short, long)]
+    pub config: Vec<String>,
 
     #[arg(short, long, default_value_t = u8::MAX)]
     pub threads: u8,
+
+    #[arg(short, long)]
+    pub seed: Option<u64>,
+
+    #[arg(short, long)]
+    pub watch: bool,
+
+    #[arg(long)]
+    pub no_scripts: bool,
+
+    #[arg(long)]
+    pub continue_on_error: bool,
+
+    #[arg(long)]
+    pub script_header: bool,
+
+    #[arg(long)]
+    pub events_file: Option<String>,
+
+    /// Run the template first and report every combination against it
+    #[arg(long)]
+    pub baseline: bool,
+
+    /// Why this sweep is being run, kept with its results
+    #[arg(long)]
+    pub note: Option<String>,
+
+    /// Take the variables from a csv with a header row, e.g. name,start,end,step,kind
+    #[arg(long)]
+    pub ranges_csv: Option<String>,
+
+    /// Abort if the script has changes that aren't committed to git
+    #[arg(long)]
+    pub require_clean_git: bool,
+
+    /// Whether to redraw the progress line in place, auto does when stderr is a terminal
+    #[arg(long, value_enum)]
+    pub progress: Option<Progress>,
+
+    /// Add to results_csv rather than replacing it, the columns have to match
+    #[arg(long)]
+    pub append: bool,
+
+    /// Tag for this sweep's rows in results_csv
+    #[arg(long)]
+    pub tag: Option<String>,
+
+    /// Separator between results_csv fields
+    #[arg(long)]
+    pub csv_delimiter: Option<char>,
+
+    /// Which results_csv fields to quote
+    #[arg(long, value_enum)]
+    pub csv_quote: Option<CsvQuote>,
+
+    /// With no variables, run the template once instead of erroring
+    #[arg(long)]
+    pub allow_empty: bool,
+
+    /// Narrow every variable's range around the best in state_file
+    #[arg(long)]
+    pub refine: bool,
+
+    /// Carry on from where an interrupted run of the same config got to
+    #[arg(long)]
+    pub resume: bool,
+
+    /// Run only the first n combinations, to try out a config before the full sweep
+    #[arg(long)]
+    pub max_runs: Option<usize>,
+
+    /// Fail the sweep once it's finished if fewer combinations than this produced a profit
+    #[arg(long)]
+    pub min_successes: Option<usize>,
+
+    /// Fail the sweep once it's finished if less than this fraction of combinations produced a profit
+    #[arg(long)]
+    pub min_success_rate: Option<f64>,
+
+    /// Print what a sweep would do and exit without running it
+    #[arg(long)]
+    pub explain: bool,
+
+    /// Run only every combination whose index is i mod n, given as i/n
+    #[arg(long, value_parser = parse_shard, conflicts_with = "index_range")]
+    pub shard: Option<Slice>,
+
+    /// Run only the combinations with indices from a up to but not including b, given as a:b
+    #[arg(long, value_parser = parse_index_range)]
+    pub index_range: Option<Slice>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Print what the configured parsers extract from a saved backtester output
+    ParseTest {
+        #[arg(short, long)]
+        input: String,
+    },
+    /// Find the overall best of a sweep split with --shard or --index-range
+    Merge {
+        /// The slices' results csvs, or the logs dirs they were run into
+        #[arg(long, required = true, num_args = 1..)]
+        inputs: Vec<String>,
+
+        /// Write every merged evaluation to this results csv
+        #[arg(short, long)]
+        out: Option<String>,
+    },
+    /// Bundle a finished sweep's manifests, config, results csv and state into one .tar.gz
+    Archive {
+        /// The archive to write, e.g. sweep.tar.gz
+        out: String,
+
+        /// Include every run's log and script as well
+        #[arg(long)]
+        logs: bool,
+    },
+    /// Time the first combination at several thread counts, to pick --threads
+    Bench {
+        /// Runs at each thread count
+        #[arg(long, default_value_t = 20)]
+        combos: usize,
+
+        /// Thread counts to try, e.g. 1,4,8, doubling up to --threads if not given
+        #[arg(long, value_delimiter = ',')]
+        thread_counts: Vec<usize>,
+    },
 }
 
 pub fn get_opts() -> Options {
     let mut args = Options::try_parse().unwrap();
 
-    if args.config == "" {
-        args.config = String::from("config.json");
+    if args.config.is_empty() {
+        args.config = vec![String::from("config.json")];
     }
 
+    args.config = match expand_config_globs(&args.config) {
+        Ok(configs) => configs,
+        Err(why) => Options::command()
+            .error(ErrorKind::ValueValidation, why)
+            .exit(),
+    };
+
     if args.threads == u8::MAX {
         args.threads = rayon::current_num_threads() as u8;
     }
 
     args
 }
+
+fn expand_config_globs(patterns: &[String]) -> Result<Vec<String>, String> {
+    let mut configs = Vec::new();
+
+    for pattern in patterns {
+        if !pattern.contains(['*', '?', '[']) {
+            configs.push(pattern.clone());
+            continue;
+        }
+
+        let mut matches = glob::glob(pattern)
+            .map_err(|why| format!("{} isn't a valid pattern: {}", pattern, why))?
+            .map(|path| {
+                path.map(|path| path.to_string_lossy().into_owned())
+                    .map_err(|why| why.to_string())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if matches.is_empty() {
+            return Err(format!("no config files match {}", pattern));
+        }
+
+        matches.sort();
+        configs.extend(matches);
+    }
+
+    Ok(configs)
+}
+
+fn parse_shard(arg: &str) -> Result<Slice, String> {
+    let (index, count) = arg.split_once('/').ok_or("expected i/n")?;
+    let index: usize = index.parse().map_err(|_| "shard index isn't a number")?;
+    let count: usize = count.parse().map_err(|_| "shard count isn't a number")?;
+
+    if index >= count {
+        return Err(format!(
+            "shards are numbered from 0 to {}",
+            count.saturating_sub(1)
+        ));
+    }
+
+    Ok(Slice::Shard { index, count })
+}
+
+fn parse_index_range(arg: &str) -> Result<Slice, String> {
+    let (start, end) = arg.split_once(':').ok_or("expected a:b")?;
+    let start: usize = start.parse().map_err(|_| "range start isn't a number")?;
+    let end: usize = end.parse().map_err(|_| "range end isn't a number")?;
+
+    if start >= end {
+        return Err(String::from("the range is empty"));
+    }
+
+    Ok(Slice::Range { start, end })
+}
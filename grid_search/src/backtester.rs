@@ -0,0 +1,72 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+pub const BACKTESTER: &str = "prosperity3bt";
+pub const BACKTESTER_ENV_VAR: &str = "PROSPERITY3BT";
+
+pub fn default_search_paths() -> Vec<String> {
+    vec![
+        String::from("~/.local/bin"),
+        String::from("$VIRTUAL_ENV/bin"),
+    ]
+}
+
+pub fn find_backtester(search_paths: &[String]) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if let Some(path) = find_on_path() {
+        return Ok(path);
+    }
+
+    if let Ok(path) = env::var(BACKTESTER_ENV_VAR) {
+        let path = PathBuf::from(path);
+
+        if path.is_file() {
+            return Ok(path);
+        }
+    }
+
+    for dir in search_paths {
+        if let Some(dir) = expand_search_path(dir) {
+            let path = dir.join(BACKTESTER);
+
+            if path.is_file() {
+                return Ok(path);
+            }
+        }
+    }
+
+    Err(format!(
+        "couldn't find {} on PATH, in ${} or in any of {:?}",
+        BACKTESTER, BACKTESTER_ENV_VAR, search_paths
+    )
+    .into())
+}
+
+fn find_on_path() -> Option<PathBuf> {
+    let path_var = env::var_os("PATH")?;
+
+    env::split_paths(&path_var)
+        .map(|dir| dir.join(BACKTESTER))
+        .find(|path| path.is_file())
+}
+
+// expands a leading `~` and any `$VAR` components, skipping the entry if a
+// variable it references isn't set
+fn expand_search_path(dir: &str) -> Option<PathBuf> {
+    let mut expanded = PathBuf::new();
+
+    for (i, component) in dir.split('/').enumerate() {
+        if i == 0 && component == "~" {
+            expanded.push(env::var("HOME").ok()?);
+        } else if let Some(var) = component.strip_prefix('$') {
+            expanded.push(env::var(var).ok()?);
+        } else if i == 0 && component.is_empty() {
+            expanded.push(Path::new("/"));
+        } else {
+            expanded.push(component);
+        }
+    }
+
+    Some(expanded)
+}
@@ -0,0 +1,109 @@
+use std::{cmp::Ordering, collections::BinaryHeap, fs};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    evaluation::{Evaluation, RunOutcome},
+    get_log_path, get_script_path,
+};
+
+// what to do once logs_dir grows past max_output_bytes
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPolicy {
+    // delete the script and log of the lowest profit combinations
+    #[default]
+    Evict,
+    // stop the sweep
+    Abort,
+}
+
+// tracks how much the sweep has written, evicting as it goes over
+pub struct OutputBudget {
+    limit: u64,
+    policy: BudgetPolicy,
+    used: u64,
+    evictable: BinaryHeap<Written>,
+    pub evicted: usize,
+}
+
+// ordered so the lowest profit is at the top of the heap
+struct Written {
+    profit: f64,
+    index: usize,
+    bytes: u64,
+}
+
+impl Ord for Written {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.profit.total_cmp(&self.profit)
+    }
+}
+
+impl PartialOrd for Written {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Written {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Written {}
+
+impl OutputBudget {
+    pub fn new(limit: u64, policy: BudgetPolicy) -> OutputBudget {
+        OutputBudget {
+            limit,
+            policy,
+            used: 0,
+            evictable: BinaryHeap::new(),
+            evicted: 0,
+        }
+    }
+
+    pub fn record(&mut self, evaluation: &Evaluation, cfg: &Config) -> Result<(), String> {
+        let bytes = combination_bytes(evaluation.index, cfg);
+        self.used += bytes;
+
+        // failures are kept, their logs are the ones worth reading
+        if evaluation.outcome == RunOutcome::Success {
+            self.evictable.push(Written {
+                profit: evaluation.profit.unwrap(),
+                index: evaluation.index,
+                bytes,
+            });
+        }
+
+        while self.used > self.limit {
+            // the best so far is never evicted
+            if self.policy == BudgetPolicy::Abort || self.evictable.len() <= 1 {
+                return Err(format!(
+                    "logs_dir has grown past max_output_bytes ({} bytes)",
+                    self.limit
+                ));
+            }
+
+            let lowest = self.evictable.pop().unwrap();
+            let _ = fs::remove_file(get_script_path(lowest.index, cfg));
+            let _ = fs::remove_file(get_log_path(lowest.index, cfg));
+
+            self.used -= lowest.bytes;
+            self.evicted += 1;
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn combination_bytes(i: usize, cfg: &Config) -> u64 {
+    [get_script_path(i, cfg), get_log_path(i, cfg)]
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
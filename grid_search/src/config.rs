@@ -1,27 +1,1238 @@
+use std::collections::{BTreeMap, HashSet};
+
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
 
-use crate::read_file;
+use crate::{
+    OBJECTIVE_METRICS, analysis::TieBreak, backtester::default_search_paths, budget::BudgetPolicy,
+    expr::parse_expr, float_range::range_len, read_file, results_csv::RESULTS_CSV_FIELDS,
+};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Config {
     pub script: String,
+
+    // can be left out when they come from --ranges-csv
+    #[serde(default)]
     pub variables: Vec<VariableConfig>,
     pub logs_dir: String,
     pub round: u8,
+
+    #[serde(default = "default_search_paths")]
+    pub backtester_search_paths: Vec<String>,
+
+    #[serde(default)]
+    pub strategy: Strategy,
+
+    // the grid's loops from outermost to innermost, which changes fastest,
+    // naming every variable once. the declared order if empty
+    #[serde(default)]
+    pub order: Vec<String>,
+
+    // only used by the sampling strategies
+    #[serde(default)]
+    pub n_samples: usize,
+
+    #[serde(default)]
+    pub seed: Option<u64>,
+
+    #[serde(default)]
+    pub plateau_epsilon: f64,
+
+    // picks the best among profits within plateau_epsilon of each other. an
+    // exact tie under the rule goes to the lowest combination index, so live,
+    // resumed and merged sweeps all settle on the same winner
+    #[serde(default)]
+    pub tie_break: TieBreak,
+
+    #[serde(default)]
+    pub capture_tail_lines: Option<usize>,
+
+    // arguments passed to the backtester, `{script}`, `{round}`, `{warmup}`
+    // and `{seed}` are substituted per run
+    #[serde(default = "default_backtester_args")]
+    pub backtester_args: Vec<String>,
+
+    #[serde(default)]
+    pub warmup: Option<u32>,
+
+    #[serde(default)]
+    pub abort_on_low_disk: bool,
+
+    #[serde(default)]
+    pub script_delivery: ScriptDelivery,
+
+    // number of combinations grouped into each `N-M` directory under logs_dir
+    #[serde(default = "default_bucket_size")]
+    pub bucket_size: usize,
+
+    #[serde(default)]
+    pub worker_mode: WorkerMode,
+
+    // arguments that start the backtester as a long-lived pool worker, see
+    // `pool` for the protocol it has to speak
+    #[serde(default)]
+    pub worker_args: Vec<String>,
+
+    #[serde(default)]
+    pub on_parse_failure: ParseFailure,
+
+    // record combinations that error and keep going instead of aborting
+    #[serde(default)]
+    pub continue_on_error: bool,
+
+    // regex run over the backtester's stdout, the first capture group is the
+    // profit (commas are stripped before parsing)
+    #[serde(default = "default_profit_pattern")]
+    pub profit_pattern: String,
+
+    // profit_pattern has to match a whole line, give or take surrounding
+    // whitespace, so chatty output mentioning it part way through a line
+    // isn't taken for the profit
+    #[serde(default)]
+    pub strict_parse: bool,
+
+    #[serde(default)]
+    pub profit_match: ProfitMatch,
+
+    // how much a profit has to beat the best so far by to count as a new best
+    #[serde(default)]
+    pub min_improvement: f64,
+
+    // where the best combination is recorded as it's found
+    #[serde(default)]
+    pub state_file: Option<String>,
+
+    // comments delimiting the constants block in the script
+    #[serde(default = "default_start_marker")]
+    pub start_marker: String,
+
+    #[serde(default = "default_end_marker")]
+    pub end_marker: String,
+
+    // prepend a comment naming the combination to each generated script
+    #[serde(default)]
+    pub script_header: bool,
+
+    // combinations that don't beat this get a one line log instead of the
+    // full output
+    #[serde(default)]
+    pub log_threshold: Option<f64>,
+
+    // constants computed from the swept variables rather than swept themselves
+    #[serde(default)]
+    pub derived: Vec<DerivedConfig>,
+
+    // kill a backtester that runs longer than this
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+
+    // ask a backtester to stop with SIGTERM after this long so it can flush
+    // what it has, then kill it at hard_timeout_secs if it's still going
+    #[serde(default)]
+    pub soft_timeout_secs: Option<u64>,
+
+    // the same as timeout_secs, named to pair with soft_timeout_secs
+    #[serde(default)]
+    pub hard_timeout_secs: Option<u64>,
+
+    // parsed profits are rounded to this many decimals so float noise can't
+    // make a new best
+    #[serde(default)]
+    pub round_profit_dp: Option<usize>,
+
+    // for backtesters that report a loss rather than a profit, applied to the
+    // parsed number before rounding
+    #[serde(default)]
+    pub negate_profit: bool,
+
+    // newline delimited json, one event per finished combination, can be a
+    // named pipe for a dashboard to read from
+    #[serde(default)]
+    pub events_file: Option<String>,
+
+    // run only part of the combinations, for splitting a sweep across machines
+    #[serde(default)]
+    pub slice: Option<Slice>,
+
+    // cap on what the scripts and logs in logs_dir can add up to
+    #[serde(default)]
+    pub max_output_bytes: Option<u64>,
+
+    #[serde(default)]
+    pub on_output_budget: BudgetPolicy,
+
+    // logs are handed to a writer thread that writes them in batches of
+    // this many, 0 has every worker write its own
+    #[serde(default)]
+    pub write_buffer: usize,
+
+    // longest a buffered log waits to be written
+    #[serde(default = "default_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+
+    // what's maximised instead of the profit, an expression over `profit`,
+    // `duration_secs` and the variables, e.g. "profit - 100 * duration_secs"
+    #[serde(default)]
+    pub objective: Option<String>,
+
+    // run the template before the grid and report combinations against it
+    #[serde(default)]
+    pub baseline: bool,
+
+    // run the baseline with these constants rather than the template's own
+    #[serde(default)]
+    pub baseline_parameters: Option<BTreeMap<String, f64>>,
+
+    // why the sweep was run, kept in the state file and the config copy in logs_dir
+    #[serde(default)]
+    pub note: Option<String>,
+
+    // also write each manifest as index.msgpack, which merge reads far faster
+    // than the json. needs the msgpack feature
+    #[serde(default)]
+    pub binary_manifests: bool,
+
+    // once the best is found, run it again on this round to see how much of
+    // its profit holds up outside the data it was picked on
+    #[serde(default)]
+    pub validation_round: Option<u8>,
+
+    // runs per combination for a backtester that isn't deterministic, each
+    // with its own seed counting up from `seed`, the mean is what's compared
+    #[serde(default = "default_repeats")]
+    pub repeats: usize,
+
+    // stop after the first this many combinations, for trying out a config
+    #[serde(default)]
+    pub max_runs: Option<usize>,
+
+    // write plot.vl.json into logs_dir, a vega-lite chart of the profits for
+    // a sweep over one or two variables
+    #[serde(default)]
+    pub plot_spec: bool,
+
+    // file keeping the average run time of the backtester command on this
+    // machine, refined by every sweep, for --explain's estimate
+    #[serde(default)]
+    pub timing_cache: Option<String>,
+
+    // refuse to sweep a script with changes that aren't committed
+    #[serde(default)]
+    pub require_clean_git: bool,
+
+    // filled in when the sweep starts, so the config copy in logs_dir records
+    // which version of the script the results are for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script_commit: Option<String>,
+
+    // a json file the backtester writes its results to, read for the profit
+    // before falling back to profit_pattern on stdout
+    #[serde(default)]
+    pub results_json: Option<ResultsJson>,
+
+    // stop starting combinations once one's profit, or objective, reaches
+    // this. compared after negate_profit, so it's always a lower bound
+    #[serde(default)]
+    pub target_objective: Option<f64>,
+
+    // combinations never to run, e.g. ones known to crash the backtester. an
+    // entry only naming some variables blocks every combination with those values
+    #[serde(default)]
+    pub blocklist: Vec<BTreeMap<String, f64>>,
+
+    #[serde(default)]
+    pub progress: Progress,
+
+    // a csv with a row per combination, written when the sweep finishes
+    #[serde(default)]
+    pub results_csv: Option<String>,
+
+    // add to results_csv rather than replacing it, as long as its header
+    // matches this sweep's columns
+    #[serde(default)]
+    pub append_results: bool,
+
+    // results_csv's tag column, telling appended sweeps apart. the time the
+    // results were written if not set
+    #[serde(default)]
+    pub run_tag: Option<String>,
+
+    // e.g. ';' for spreadsheets in locales using a decimal comma
+    #[serde(default = "default_csv_delimiter")]
+    pub csv_delimiter: char,
+
+    #[serde(default)]
+    pub csv_quote: CsvQuote,
+
+    // results_csv's columns in order, from the variables, derived constants
+    // and RESULTS_CSV_FIELDS. empty writes the fixed fields, then the
+    // variables and derived constants as declared
+    #[serde(default)]
+    pub csv_columns: Vec<String>,
+
+    // with no variables, run the template once as it is rather than refusing
+    #[serde(default)]
+    pub allow_empty: bool,
+
+    // carry on from the cursor an earlier run of this sweep left in logs_dir
+    #[serde(default)]
+    pub resume: bool,
+
+    // program and arguments writing the constants block itself, given the
+    // parameters as a json object on stdin, for more than `name = value` lines
+    #[serde(default)]
+    pub constants_command: Option<Vec<String>>,
+
+    // a sweep with fewer combinations producing a profit than this, as a
+    // count or a fraction of those run, fails once it's finished
+    #[serde(default)]
+    pub min_successes: Option<usize>,
+
+    #[serde(default)]
+    pub min_success_rate: Option<f64>,
+
+    // run once before the sweep, e.g. to download data or warm a cache, the
+    // sweep doesn't start if it fails
+    #[serde(default)]
+    pub pre_command: Option<PreCommand>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PreCommand {
+    // program and arguments
+    pub command: Vec<String>,
+
+    // working directory, the sweep's own if not set
+    #[serde(default)]
+    pub dir: Option<String>,
+
+    // set on top of the sweep's environment
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResultsJson {
+    // `{index}` and `{script}` are substituted per run, so runs in parallel
+    // don't read each other's results
+    pub path: String,
+
+    // json pointer to the profit, e.g. "/summary/total_pnl"
+    #[serde(default = "default_results_profit")]
+    pub profit: String,
+}
+
+fn default_results_profit() -> String {
+    String::from("/profit")
+}
+
+// whether the progress line is redrawn in place, auto only does so when
+// stderr is a terminal
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Progress {
+    Always,
+    #[default]
+    Auto,
+    Never,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+// which results_csv fields are wrapped in quotes
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum CsvQuote {
+    // only those holding the delimiter, a quote or a newline
+    #[default]
+    Necessary,
+    Always,
+    // the header and text fields like the tag and outcome
+    NonNumeric,
+    Never,
+}
+
+fn default_csv_delimiter() -> char {
+    ','
+}
+
+// a variable with this name picks the round each combination is backtested
+// on, passed as `{round}` rather than written into the script
+pub const ROUND_VARIABLE: &str = "round";
+
+fn default_flush_interval_ms() -> u64 {
+    200
+}
+
+fn default_repeats() -> usize {
+    1
+}
+
+fn default_start_marker() -> String {
+    String::from("# start")
+}
+
+fn default_end_marker() -> String {
+    String::from("# end")
+}
+
+fn default_profit_pattern() -> String {
+    String::from(r"Total profit:\s*([\d,]+)")
+}
+
+// which profit counts when the pattern matches more than one line
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProfitMatch {
+    #[default]
+    First,
+    Last,
+    Max,
+    // for backtesters that print a profit per day rather than a total
+    Sum,
+}
+
+// what to do with a combination whose output has no profit line
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ParseFailure {
+    // record it with a profit of 0
+    #[default]
+    Zero,
+    // leave it out of the best and any stats
+    Skip,
+    // stop the sweep
+    Abort,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkerMode {
+    // a fresh backtester process per combination
+    #[default]
+    Spawn,
+    // one persistent backtester per thread, fed combinations over stdin
+    Pool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Slice {
+    // every index that is `index` mod `count`
+    Shard { index: usize, count: usize },
+    // indices from `start` up to but not including `end`
+    Range { start: usize, end: usize },
+}
+
+impl Config {
+    // however the kill timeout was spelled
+    pub fn kill_after_secs(&self) -> Option<u64> {
+        self.hard_timeout_secs.or(self.timeout_secs)
+    }
+
+    // profit_pattern as it's run, anchored to a line under strict_parse
+    pub fn profit_regex(&self) -> String {
+        match self.strict_parse {
+            true => format!(r"(?m)^\s*(?:{})\s*$", self.profit_pattern),
+            false => self.profit_pattern.clone(),
+        }
+    }
+}
+
+impl Slice {
+    pub fn contains(&self, i: usize) -> bool {
+        match *self {
+            Slice::Shard { index, count } => i % count == index,
+            Slice::Range { start, end } => (start..end).contains(&i),
+        }
+    }
+
+    // tells the outputs of different slices of the same sweep apart
+    pub fn tag(&self) -> String {
+        match self {
+            Slice::Shard { index, count } => format!("shard-{}-of-{}", index, count),
+            Slice::Range { start, end } => format!("range-{}-{}", start, end),
+        }
+    }
+}
+
+fn default_bucket_size() -> usize {
+    100
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptDelivery {
+    // written into logs_dir and kept
+    #[default]
+    Disk,
+    // written to a RAM-backed temp dir and deleted after the run
+    Ram,
+    // piped to the backtester's stdin, with `{script}` passed as `-`
+    Stdin,
+}
+
+fn default_backtester_args() -> Vec<String> {
+    vec![String::from("{script}"), String::from("{round}")]
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    #[default]
+    Grid,
+    Lhs,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct VariableConfig {
     pub name: String,
     pub start: f64,
     pub end: f64,
-    pub step: f64,
+
+    // exactly one of the two, `count` spreads that many values evenly from
+    // start to end, end included
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+
+    // with `step`, also run `end` itself when the steps from start don't land on it
+    #[serde(default)]
+    pub force_endpoints: bool,
+
+    #[serde(default)]
+    pub format: ValueFormat,
+
+    // decimal places for `fixed`, significant figures for `sig`
+    #[serde(default)]
+    pub digits: Option<usize>,
+
+    // only swept while an earlier variable has one of the given values, held
+    // at its first value otherwise so the duplicate runs collapse
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_when: Option<ActiveWhen>,
+
+    // how the variable is shown in the summary and plots, the script always
+    // gets `name`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ActiveWhen {
+    pub variable: String,
+    pub values: Vec<f64>,
+}
+
+impl ActiveWhen {
+    // within a rounding error, as the values come out of a stepped range
+    pub fn matches(&self, value: f64) -> bool {
+        self.values.iter().any(|v| (v - value).abs() < 1e-9)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueFormat {
+    #[default]
+    Fixed,
+    Sig,
+    Auto,
+}
+
+const DEFAULT_FIXED_DIGITS: usize = 3;
+const DEFAULT_SIG_DIGITS: usize = 6;
+const INTEGRAL_TOLERANCE: f64 = 1e-9;
+
+impl VariableConfig {
+    pub fn format_value(&self, value: f64) -> String {
+        format_value(value, self.format, self.digits)
+    }
+
+    // e.g. "Take Width (ticks)", falling back to the name
+    pub fn display_name(&self) -> String {
+        let label = self.label.as_deref().unwrap_or(&self.name);
+
+        match &self.unit {
+            Some(unit) => format!("{} ({})", label, unit),
+            None => label.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DerivedConfig {
+    pub name: String,
+
+    // arithmetic over variables and earlier derived constants
+    pub expr: String,
+
+    #[serde(default)]
+    pub format: ValueFormat,
+
+    #[serde(default)]
+    pub digits: Option<usize>,
+}
+
+impl DerivedConfig {
+    pub fn format_value(&self, value: f64) -> String {
+        format_value(value, self.format, self.digits)
+    }
+}
+
+fn format_value(value: f64, format: ValueFormat, digits: Option<usize>) -> String {
+    match format {
+        // json doesn't tell 20 from 20.0, so whole values are written as ints
+        // for scripts that index with them, unless digits asks for decimals
+        ValueFormat::Fixed if digits.is_none() && is_integral(value) => {
+            format!("{}", value.round() + 0.)
+        }
+        ValueFormat::Fixed => {
+            format!("{:.*}", digits.unwrap_or(DEFAULT_FIXED_DIGITS), value)
+        }
+        ValueFormat::Sig => {
+            let digits = digits.unwrap_or(DEFAULT_SIG_DIGITS).max(1);
+            let rounded: f64 = format!("{:.*e}", digits - 1, value).parse().unwrap();
+
+            format!("{}", rounded)
+        }
+        // rust's float display is the shortest string that round trips
+        ValueFormat::Auto => format!("{}", value),
+    }
+}
+
+// within float noise of a whole number, as stepped values come out of sums
+fn is_integral(value: f64) -> bool {
+    value.is_finite() && (value - value.round()).abs() < INTEGRAL_TOLERANCE
+}
+
+// builds a validated config in code, with every field not set getting the
+// same default as when it's left out of a config file
+pub struct ConfigBuilder {
+    cfg: Config,
+}
+
+impl ConfigBuilder {
+    pub fn new() -> ConfigBuilder {
+        let required = json!({ "script": "", "variables": [], "logs_dir": "", "round": 0 });
+
+        ConfigBuilder {
+            cfg: serde_json::from_value(required).unwrap(),
+        }
+    }
+
+    pub fn script(mut self, script: &str) -> ConfigBuilder {
+        self.cfg.script = script.to_string();
+        self
+    }
+
+    pub fn logs_dir(mut self, logs_dir: &str) -> ConfigBuilder {
+        self.cfg.logs_dir = logs_dir.to_string();
+        self
+    }
+
+    pub fn round(mut self, round: u8) -> ConfigBuilder {
+        self.cfg.round = round;
+        self
+    }
+
+    pub fn add_variable(mut self, name: &str, start: f64, end: f64, step: f64) -> ConfigBuilder {
+        self.cfg.variables.push(VariableConfig {
+            name: name.to_string(),
+            start,
+            end,
+            step: Some(step),
+            count: None,
+            force_endpoints: false,
+            format: ValueFormat::default(),
+            digits: None,
+            active_when: None,
+            label: None,
+            unit: None,
+        });
+        self
+    }
+
+    pub fn add_variable_count(
+        mut self,
+        name: &str,
+        start: f64,
+        end: f64,
+        count: usize,
+    ) -> ConfigBuilder {
+        self.cfg.variables.push(VariableConfig {
+            name: name.to_string(),
+            start,
+            end,
+            step: None,
+            count: Some(count),
+            force_endpoints: false,
+            format: ValueFormat::default(),
+            digits: None,
+            active_when: None,
+            label: None,
+            unit: None,
+        });
+        self
+    }
+
+    // makes the variable called `name` only active while `variable` is one of `values`
+    pub fn active_when(mut self, name: &str, variable: &str, values: &[f64]) -> ConfigBuilder {
+        if let Some(var) = self.cfg.variables.iter_mut().find(|v| v.name == name) {
+            var.active_when = Some(ActiveWhen {
+                variable: variable.to_string(),
+                values: values.to_vec(),
+            });
+        }
+        self
+    }
+
+    pub fn add_derived(mut self, name: &str, expr: &str) -> ConfigBuilder {
+        self.cfg.derived.push(DerivedConfig {
+            name: name.to_string(),
+            expr: expr.to_string(),
+            format: ValueFormat::default(),
+            digits: None,
+        });
+        self
+    }
+
+    pub fn strategy(mut self, strategy: Strategy, n_samples: usize) -> ConfigBuilder {
+        self.cfg.strategy = strategy;
+        self.cfg.n_samples = n_samples;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> ConfigBuilder {
+        self.cfg.seed = Some(seed);
+        self
+    }
+
+    pub fn profit_pattern(mut self, pattern: &str) -> ConfigBuilder {
+        self.cfg.profit_pattern = pattern.to_string();
+        self
+    }
+
+    pub fn markers(mut self, start: &str, end: &str) -> ConfigBuilder {
+        self.cfg.start_marker = start.to_string();
+        self.cfg.end_marker = end.to_string();
+        self
+    }
+
+    pub fn backtester_args(mut self, args: &[&str]) -> ConfigBuilder {
+        self.cfg.backtester_args = args.iter().map(|a| a.to_string()).collect();
+        self
+    }
+
+    pub fn state_file(mut self, state_file: &str) -> ConfigBuilder {
+        self.cfg.state_file = Some(state_file.to_string());
+        self
+    }
+
+    pub fn timeout_secs(mut self, timeout_secs: u64) -> ConfigBuilder {
+        self.cfg.timeout_secs = Some(timeout_secs);
+        self
+    }
+
+    pub fn soft_timeout_secs(mut self, soft_timeout_secs: u64) -> ConfigBuilder {
+        self.cfg.soft_timeout_secs = Some(soft_timeout_secs);
+        self
+    }
+
+    pub fn hard_timeout_secs(mut self, hard_timeout_secs: u64) -> ConfigBuilder {
+        self.cfg.hard_timeout_secs = Some(hard_timeout_secs);
+        self
+    }
+
+    pub fn build(self) -> Result<Config, Box<dyn std::error::Error>> {
+        validate_config(&self.cfg)?;
+
+        Ok(self.cfg)
+    }
+}
+
+impl Default for ConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 pub fn parse_config(fp: &str) -> Result<Config, Box<dyn std::error::Error>> {
     let contents = read_file(fp)?;
 
-    let config: Config = serde_json::from_str(&contents)?;
+    let mut value: Value = serde_json::from_str(&contents)?;
+    interpolate_env(&mut value)?;
+    let config: Config = serde_json::from_value(value)?;
+
+    validate_config(&config)?;
+
+    Ok(config)
+}
+
+// replaces `${VAR}` in every string of the config with that environment
+// variable, or with what follows `:-` in `${VAR:-default}` when it isn't set,
+// so the same config can point at different paths on different machines
+fn interpolate_env(value: &mut Value) -> Result<(), String> {
+    match value {
+        Value::String(s) => {
+            let pattern = Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)(?::-([^}]*))?\}").unwrap();
+            let mut missing = None;
+
+            let interpolated = pattern.replace_all(s, |caps: &regex::Captures| {
+                match (std::env::var(&caps[1]), caps.get(2)) {
+                    (Ok(var), _) => var,
+                    (Err(_), Some(default)) => default.as_str().to_string(),
+                    (Err(_), None) => {
+                        missing.get_or_insert_with(|| caps[1].to_string());
+                        String::new()
+                    }
+                }
+            });
+
+            if let Some(var) = missing {
+                return Err(format!("{} uses ${{{}}}, which isn't set", s, var));
+            }
+
+            *s = interpolated.into_owned();
+        }
+        Value::Array(values) => values.iter_mut().try_for_each(interpolate_env)?,
+        Value::Object(fields) => fields.values_mut().try_for_each(interpolate_env)?,
+        _ => {}
+    }
+
+    Ok(())
+}
+
+// variables from a spreadsheet export, one per row under a header naming
+// the columns, e.g. `name,start,end,step,kind`. any VariableConfig field can
+// be a column, and empty cells are left at their defaults
+pub fn read_ranges_csv(fp: &str) -> Result<Vec<VariableConfig>, Box<dyn std::error::Error>> {
+    let contents = read_file(fp)?;
+    let mut lines = contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    let (_, header) = lines.next().ok_or("the ranges csv is empty")?;
+    let columns = header.split(',').map(str::trim).collect::<Vec<_>>();
+
+    lines
+        .map(|(i, line)| {
+            let mut row = serde_json::Map::new();
+            let cells = line.split(',').map(str::trim).collect::<Vec<_>>();
+
+            if cells.len() != columns.len() {
+                return Err(format!(
+                    "line {} has {} cells but the header has {} columns",
+                    i + 1,
+                    cells.len(),
+                    columns.len()
+                )
+                .into());
+            }
+
+            for (column, cell) in columns.iter().zip(cells).filter(|(_, c)| !c.is_empty()) {
+                let value = match *column {
+                    "name" | "format" => json!(cell),
+                    // the only kind of variable there is
+                    "kind" if cell == "float" => continue,
+                    "kind" => {
+                        return Err(format!("line {}: unsupported kind {}", i + 1, cell).into());
+                    }
+                    "start" | "end" | "step" => json!(cell.parse::<f64>().map_err(|_| format!(
+                        "line {}: {} isn't a number",
+                        i + 1,
+                        column
+                    ))?),
+                    "count" | "digits" => json!(cell.parse::<usize>().map_err(|_| format!(
+                        "line {}: {} isn't a count",
+                        i + 1,
+                        column
+                    ))?),
+                    "force_endpoints" => json!(cell.parse::<bool>().map_err(|_| format!(
+                        "line {}: {} isn't true or false",
+                        i + 1,
+                        column
+                    ))?),
+                    _ => return Err(format!("unknown column {}", column).into()),
+                };
+
+                row.insert(column.to_string(), value);
+            }
+
+            serde_json::from_value(row.into())
+                .map_err(|why| format!("line {}: {}", i + 1, why).into())
+        })
+        .collect()
+}
+
+pub fn validate_config(cfg: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let mut names = HashSet::new();
+
+    for var in &cfg.variables {
+        if names.contains(var.name.as_str()) {
+            return Err(format!("variable {} is declared more than once", var.name).into());
+        }
+
+        match (var.step, var.count) {
+            (Some(step), None) if step == 0. || !step.is_finite() => {
+                return Err(format!("variable {} has an invalid step {}", var.name, step).into());
+            }
+            (None, Some(0)) => {
+                return Err(format!("variable {} needs a count of at least 1", var.name).into());
+            }
+            (None, Some(_)) if var.force_endpoints => {
+                return Err(format!(
+                    "variable {} has force_endpoints, which only applies with a step, a count always runs both ends",
+                    var.name
+                )
+                .into());
+            }
+            (Some(_), None) | (None, Some(_)) => {}
+            _ => {
+                return Err(
+                    format!("variable {} needs exactly one of step or count", var.name).into(),
+                );
+            }
+        }
+
+        // forcing the end onto a step going the other way would run the end alone
+        if let Some(step) = var.step.filter(|_| var.force_endpoints)
+            && var.start != var.end
+            && range_len(var.start, var.end, step) == 0
+        {
+            return Err(format!(
+                "variable {} has force_endpoints, but a step of {} never gets from {} to {}",
+                var.name, step, var.start, var.end
+            )
+            .into());
+        }
+
+        if let Some(condition) = &var.active_when {
+            // checked before this variable's name is added, so it can't depend on itself
+            if !names.contains(condition.variable.as_str()) {
+                return Err(format!(
+                    "variable {} is active_when {}, which isn't an earlier variable",
+                    var.name, condition.variable
+                )
+                .into());
+            }
+
+            if condition.values.is_empty() {
+                return Err(format!("variable {} has no active_when values", var.name).into());
+            }
+
+            if cfg.strategy != Strategy::Grid {
+                return Err("active_when is only supported with the grid strategy".into());
+            }
+        }
+
+        names.insert(var.name.as_str());
+    }
+
+    if !cfg.order.is_empty() {
+        let mut nested = HashSet::new();
+
+        for name in &cfg.order {
+            let var = cfg
+                .variables
+                .iter()
+                .find(|var| &var.name == name)
+                .ok_or_else(|| format!("order has {}, which isn't a variable", name))?;
+
+            if let Some(condition) = &var.active_when
+                && !nested.contains(condition.variable.as_str())
+            {
+                return Err(format!(
+                    "order has {} outside {}, which it's active_when",
+                    name, condition.variable
+                )
+                .into());
+            }
+
+            if !nested.insert(name.as_str()) {
+                return Err(format!("order has {} more than once", name).into());
+            }
+        }
+
+        if let Some(missing) = cfg
+            .variables
+            .iter()
+            .find(|var| !nested.contains(var.name.as_str()))
+        {
+            return Err(format!("order is missing variable {}", missing.name).into());
+        }
+    }
+
+    for derived in &cfg.derived {
+        let expr = parse_expr(&derived.expr)
+            .map_err(|why| format!("derived {} has an invalid expr: {}", derived.name, why))?;
+
+        // only names declared above it, so there's always an order to evaluate in
+        if let Some(unknown) = expr.variables().into_iter().find(|v| !names.contains(*v)) {
+            return Err(format!(
+                "derived {} uses {}, which isn't a variable or an earlier derived constant",
+                derived.name, unknown
+            )
+            .into());
+        }
+
+        if !names.insert(derived.name.as_str()) {
+            return Err(format!("variable {} is declared more than once", derived.name).into());
+        }
+    }
+
+    if let Some(objective) = &cfg.objective {
+        let expr = parse_expr(objective).map_err(|why| format!("objective is invalid: {}", why))?;
+
+        if let Some(unknown) = expr
+            .variables()
+            .into_iter()
+            .find(|v| !names.contains(*v) && !OBJECTIVE_METRICS.contains(v))
+        {
+            return Err(format!(
+                "objective uses {}, which isn't a variable or metric",
+                unknown
+            )
+            .into());
+        }
+    }
+
+    let profit_re = Regex::new(&cfg.profit_regex())?;
+    if profit_re.captures_len() < 2 {
+        return Err("profit_pattern needs a capture group around the profit".into());
+    }
+
+    if cfg.start_marker.is_empty() || cfg.end_marker.is_empty() {
+        return Err("start_marker and end_marker can't be empty".into());
+    }
+
+    if cfg.binary_manifests && !cfg!(feature = "msgpack") {
+        return Err("binary_manifests needs grid_search built with the msgpack feature".into());
+    }
+
+    if let Some(var) = cfg.variables.iter().find(|var| var.name == ROUND_VARIABLE) {
+        let whole = |v: f64| v.fract() == 0. && (0. ..=u8::MAX as f64).contains(&v);
+
+        if !whole(var.start) || !whole(var.end) || !var.step.is_some_and(whole) {
+            return Err("a swept round needs whole number start, end and step".into());
+        }
+
+        if cfg.strategy != Strategy::Grid {
+            return Err("a swept round is only supported with the grid strategy".into());
+        }
+
+        if cfg.validation_round.is_some() {
+            return Err("validation_round can't be used while round is swept".into());
+        }
+    }
+
+    if !cfg.csv_delimiter.is_ascii() || cfg.csv_delimiter == '"' {
+        return Err("csv_delimiter must be a single ascii character other than a quote".into());
+    }
+
+    let mut columns = HashSet::new();
+    for column in &cfg.csv_columns {
+        let known = RESULTS_CSV_FIELDS.contains(&column.as_str())
+            || cfg.variables.iter().any(|var| &var.name == column)
+            || cfg.derived.iter().any(|derived| &derived.name == column);
+
+        if !known {
+            return Err(format!(
+                "csv_columns has {}, which isn't a variable, derived constant or one of {}",
+                column,
+                RESULTS_CSV_FIELDS.join(", ")
+            )
+            .into());
+        }
+
+        if !columns.insert(column) {
+            return Err(format!("csv_columns has {} more than once", column).into());
+        }
+    }
+
+    if cfg.constants_command.as_ref().is_some_and(|c| c.is_empty()) {
+        return Err("constants_command needs at least a program to run".into());
+    }
+
+    if cfg
+        .pre_command
+        .as_ref()
+        .is_some_and(|pre| pre.command.is_empty())
+    {
+        return Err("pre_command needs at least a program to run".into());
+    }
+
+    if cfg.append_results && cfg.results_csv.is_none() {
+        return Err("append_results needs a results_csv to append to".into());
+    }
+
+    if cfg.bucket_size == 0 {
+        return Err("bucket_size must be greater than 0".into());
+    }
+
+    if cfg.warmup.is_none() && cfg.backtester_args.iter().any(|a| a.contains("{warmup}")) {
+        return Err("backtester_args uses {warmup} but no warmup is set".into());
+    }
+
+    if let Some(unknown) = cfg
+        .blocklist
+        .iter()
+        .flat_map(|blocked| blocked.keys())
+        .find(|name| !cfg.variables.iter().any(|var| &var.name == *name))
+    {
+        return Err(format!("blocklist names {}, which isn't a variable", unknown).into());
+    }
+
+    if let Some(results) = &cfg.results_json {
+        if !results.path.contains("{index}") && !results.path.contains("{script}") {
+            return Err("results_json's path needs {index} or {script} in it".into());
+        }
+
+        if !results.profit.is_empty() && !results.profit.starts_with('/') {
+            return Err("results_json's profit is a json pointer, starting with /".into());
+        }
+    }
+
+    if cfg.max_runs == Some(0) {
+        return Err("max_runs must be at least 1".into());
+    }
+
+    if cfg
+        .min_success_rate
+        .is_some_and(|rate| !(0.0..=1.0).contains(&rate))
+    {
+        return Err("min_success_rate must be a fraction from 0 to 1".into());
+    }
+
+    if cfg.repeats == 0 {
+        return Err("repeats must be at least 1".into());
+    }
+
+    // with repeats the seeds count up from 0 when there's no seed
+    if cfg.seed.is_none()
+        && cfg.repeats == 1
+        && cfg.backtester_args.iter().any(|a| a.contains("{seed}"))
+    {
+        return Err("backtester_args uses {seed} but no seed is set".into());
+    }
+
+    if cfg.worker_mode == WorkerMode::Pool && cfg.script_delivery == ScriptDelivery::Stdin {
+        return Err("pool workers can't take scripts over stdin".into());
+    }
+
+    if cfg.timeout_secs.is_some() && cfg.hard_timeout_secs.is_some() {
+        return Err("set one of timeout_secs and hard_timeout_secs, not both".into());
+    }
+
+    if let Some(soft) = cfg.soft_timeout_secs {
+        match cfg.kill_after_secs() {
+            None => return Err("soft_timeout_secs needs a hard_timeout_secs to kill at".into()),
+            Some(hard) if soft >= hard => {
+                return Err(format!(
+                    "soft_timeout_secs ({}) must be less than hard_timeout_secs ({})",
+                    soft, hard
+                )
+                .into());
+            }
+            Some(_) => {}
+        }
+    }
+
+    if cfg.worker_mode == WorkerMode::Pool && cfg.kill_after_secs().is_some() {
+        return Err("timeouts aren't supported with pool workers".into());
+    }
+
+    if cfg.write_buffer > 0 && cfg.max_output_bytes.is_some() {
+        return Err(
+            "max_output_bytes needs logs written as they finish, so no write_buffer".into(),
+        );
+    }
+
+    match cfg.slice {
+        Some(Slice::Shard { index, count }) if index >= count => {
+            return Err(format!("shard {} doesn't exist when there are {}", index, count).into());
+        }
+        Some(Slice::Range { start, end }) if start >= end => {
+            return Err(format!("index range {}:{} is empty", start, end).into());
+        }
+        _ => {}
+    }
+
+    if cfg.strategy != Strategy::Grid && cfg.n_samples == 0 {
+        return Err("n_samples must be set for sampling strategies".into());
+    }
+
+    Ok(())
+}
+
+// describes what changed between two configs, one line per change
+pub fn diff_configs(old: &Config, new: &Config) -> Vec<String> {
+    let old = serde_json::to_value(old).unwrap();
+    let new = serde_json::to_value(new).unwrap();
+
+    let mut changes = Vec::new();
+
+    for (key, new_value) in new.as_object().unwrap() {
+        let old_value = &old[key];
+
+        if key == "variables" {
+            changes.extend(diff_variables(old_value, new_value));
+        } else if old_value != new_value {
+            changes.push(format!("~ {}: {} -> {}", key, old_value, new_value));
+        }
+    }
+
+    changes
+}
+
+fn diff_variables(old: &Value, new: &Value) -> Vec<String> {
+    let by_name = |vars: &Value| -> Vec<(String, Value)> {
+        vars.as_array()
+            .into_iter()
+            .flatten()
+            .map(|var| {
+                (
+                    var["name"].as_str().unwrap_or_default().to_string(),
+                    var.clone(),
+                )
+            })
+            .collect()
+    };
+
+    let old = by_name(old);
+    let new = by_name(new);
+
+    let mut changes = Vec::new();
+
+    for (name, var) in &new {
+        match old.iter().find(|(old_name, _)| old_name == name) {
+            None => changes.push(format!("+ variable {}: {}", name, var)),
+            Some((_, old_var)) => {
+                for (field, value) in var.as_object().into_iter().flatten() {
+                    if old_var[field] != *value {
+                        changes.push(format!(
+                            "~ variable {}.{}: {} -> {}",
+                            name, field, old_var[field], value
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    for (name, _) in &old {
+        if !new.iter().any(|(new_name, _)| new_name == name) {
+            changes.push(format!("- variable {}", name));
+        }
+    }
 
-    return Ok(config);
+    changes
 }
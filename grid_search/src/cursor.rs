@@ -0,0 +1,127 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Config, create_file, read_file, values_for};
+
+pub const CURSOR_FILE: &str = "cursor.json";
+
+// how often the cursor, and the manifests it relies on, are written while a
+// sweep runs
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(30);
+
+// how far a sweep had got, so --resume can carry on without rerunning what
+// finished. everything before `next` has finished, later combinations that
+// finished early are in the manifests written alongside it
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Cursor {
+    pub config_hash: u64,
+    pub next: usize,
+
+    // how many values each variable takes, so a resized grid is called out
+    // by name rather than as a hash mismatch
+    pub sizes: Vec<usize>,
+}
+
+pub fn cursor_path(cfg: &Config) -> PathBuf {
+    Path::new(&cfg.logs_dir).join(CURSOR_FILE)
+}
+
+pub fn save_cursor(cfg: &Config, next: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let cursor = Cursor {
+        config_hash: config_hash(cfg),
+        next,
+        sizes: sizes(cfg),
+    };
+
+    create_file(&serde_json::to_string_pretty(&cursor)?, &cursor_path(cfg))?;
+
+    Ok(())
+}
+
+// the saved cursor, as long as it was saved by this same sweep
+pub fn load_cursor(cfg: &Config) -> Result<Cursor, Box<dyn std::error::Error>> {
+    let path = cursor_path(cfg);
+    let contents = read_file(&path.to_string_lossy()).map_err(|why| {
+        format!(
+            "nothing to resume, couldn't read {}: {}",
+            path.display(),
+            why
+        )
+    })?;
+    let cursor: Cursor = serde_json::from_str(&contents)?;
+
+    if cursor.sizes != sizes(cfg) {
+        return Err(format!(
+            "can't resume, the variables took {:?} values each but now take {:?}",
+            cursor.sizes,
+            sizes(cfg)
+        )
+        .into());
+    }
+
+    if cursor.config_hash != config_hash(cfg) {
+        return Err("can't resume, the config has changed since the cursor was saved".into());
+    }
+
+    Ok(cursor)
+}
+
+// the first selected combination that hasn't finished, or just past the last
+// once they all have
+pub fn next_unfinished(selected: &[usize], finished: &HashSet<usize>) -> usize {
+    selected
+        .iter()
+        .copied()
+        .find(|i| !finished.contains(i))
+        .unwrap_or_else(|| selected.last().map_or(0, |last| last + 1))
+}
+
+fn sizes(cfg: &Config) -> Vec<usize> {
+    cfg.variables
+        .iter()
+        .map(|var| values_for(var).len())
+        .collect()
+}
+
+// fnv-1a over the config as json, as std's hasher isn't stable across
+// releases. fields that don't change which combinations run, or how, are
+// left out so a resume can change them
+fn config_hash(cfg: &Config) -> u64 {
+    let mut cfg = cfg.clone();
+    cfg.note = None;
+    cfg.events_file = None;
+    cfg.progress = Default::default();
+    cfg.resume = false;
+    cfg.max_runs = None;
+
+    fnv_hash(&serde_json::to_string(&cfg).unwrap_or_default())
+}
+
+// only what decides which combinations there are, which index each has and
+// how it's scored, so slices run with different output paths, notes or
+// commits still match
+pub fn sweep_hash(cfg: &Config) -> u64 {
+    let swept = serde_json::json!({
+        "variables": cfg.variables,
+        "order": cfg.order,
+        "derived": cfg.derived,
+        "strategy": cfg.strategy,
+        "n_samples": cfg.n_samples,
+        "seed": cfg.seed,
+        "script": cfg.script,
+        "objective": cfg.objective,
+    });
+
+    fnv_hash(&swept.to_string())
+}
+
+fn fnv_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
@@ -0,0 +1,80 @@
+use std::{io, path::Path};
+
+// free bytes available to unprivileged users on the filesystem holding `path`
+#[cfg(unix)]
+pub fn available_space(path: &Path) -> Option<u64> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+pub fn available_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+// warns, or errors when `abort` is set, if `needed` bytes won't fit next to `path`
+pub fn check_space(path: &Path, needed: u64, abort: bool) -> Result<(), String> {
+    let Some(available) = available_space(path) else {
+        return Ok(());
+    };
+
+    if needed <= available {
+        return Ok(());
+    }
+
+    let msg = format!(
+        "the sweep is expected to write about {} to {} but only {} is free",
+        format_bytes(needed),
+        path.display(),
+        format_bytes(available)
+    );
+
+    if abort {
+        return Err(msg);
+    }
+
+    eprintln!("warning: {}", msg);
+    Ok(())
+}
+
+pub fn is_disk_full(err: &io::Error) -> bool {
+    #[cfg(unix)]
+    if err.raw_os_error() == Some(libc::ENOSPC) {
+        return true;
+    }
+
+    err.kind() == io::ErrorKind::StorageFull
+}
+
+pub fn describe_write_error(path: &Path, err: &io::Error) -> String {
+    if is_disk_full(err) {
+        format!(
+            "the disk holding {} is full, free up space or point logs_dir somewhere else and rerun",
+            path.display()
+        )
+    } else {
+        format!("couldn't write to {}: {}", path.display(), err)
+    }
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+
+    while value >= 1024. && unit < UNITS.len() - 1 {
+        value /= 1024.;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", value, UNITS[unit])
+}
@@ -0,0 +1,73 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunOutcome {
+    Success,
+    NoProfit,
+    Error,
+    Timeout,
+    // stopped at the soft timeout, but flushed a profit it's scored on
+    SoftTimeout,
+    Skipped,
+    // the profit parsed as inf or nan
+    NonFinite,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Evaluation {
+    pub index: usize,
+    pub parameters: BTreeMap<String, f64>,
+    pub profit: Option<f64>,
+    pub outcome: RunOutcome,
+
+    // with an objective `profit` holds its value, and this the backtester's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_profit: Option<f64>,
+
+    // how far `profit` is above the baseline run's, when there was one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub baseline_delta: Option<f64>,
+
+    // spread of `profit` across the runs, with repeats
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profit_std: Option<f64>,
+
+    #[serde(default)]
+    pub duration_secs: f64,
+
+    // tail of stderr for runs that didn't produce a profit, or what went wrong
+    // for runs that errored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// reads `key = number` lines back into a map, ignoring trailing comments and
+// skipping anything else. keys keep their case since they're python names
+pub fn parse_constants(constants: &str) -> BTreeMap<String, f64> {
+    constants
+        .lines()
+        .filter_map(|line| {
+            let line = line.split('#').next()?;
+            let (key, value) = line.split_once('=')?;
+
+            let key = key.trim();
+            let value = value.trim().parse::<f64>().ok()?;
+
+            if !is_identifier(key) {
+                return None;
+            }
+
+            Some((key.to_string(), value))
+        })
+        .collect()
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
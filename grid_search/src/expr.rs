@@ -0,0 +1,153 @@
+// arithmetic over variable names: numbers, identifiers, + - * / and
+// parentheses, with the usual precedence
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Number(f64),
+    Variable(String),
+    Neg(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    pub fn eval(&self, values: &HashMap<String, f64>) -> Result<f64, String> {
+        Ok(match self {
+            Expr::Number(n) => *n,
+            Expr::Variable(name) => *values
+                .get(name)
+                .ok_or_else(|| format!("unknown variable {}", name))?,
+            Expr::Neg(inner) => -inner.eval(values)?,
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(values)?, rhs.eval(values)?);
+
+                match op {
+                    '+' => lhs + rhs,
+                    '-' => lhs - rhs,
+                    '*' => lhs * rhs,
+                    _ => lhs / rhs,
+                }
+            }
+        })
+    }
+
+    pub fn variables(&self) -> Vec<&str> {
+        match self {
+            Expr::Number(_) => Vec::new(),
+            Expr::Variable(name) => vec![name],
+            Expr::Neg(inner) => inner.variables(),
+            Expr::Binary(_, lhs, rhs) => {
+                let mut res = lhs.variables();
+                res.extend(rhs.variables());
+                res
+            }
+        }
+    }
+}
+
+pub fn parse_expr(input: &str) -> Result<Expr, String> {
+    let tokens = tokenize(input)?;
+    let mut pos = 0;
+
+    let expr = parse_sum(&tokens, &mut pos)?;
+
+    if pos != tokens.len() {
+        return Err(format!("unexpected {:?} in {:?}", tokens[pos], input));
+    }
+
+    Ok(expr)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if "+-*/()".contains(c) {
+            tokens.push(Token::Op(c));
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+                number.push(c);
+                chars.next();
+            }
+
+            let number = number
+                .parse()
+                .map_err(|_| format!("invalid number {:?} in {:?}", number, input))?;
+            tokens.push(Token::Number(number));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut ident = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+                ident.push(c);
+                chars.next();
+            }
+
+            tokens.push(Token::Ident(ident));
+        } else {
+            return Err(format!("unexpected {:?} in {:?}", c, input));
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_sum(tokens: &[Token], pos: &mut usize) -> Result<Expr, String> {
+    let mut lhs = parse_product(tokens, pos)?;
+
+    while let Some(Token::Op(op @ ('+' | '-'))) = tokens.get(*pos) {
+        *pos += 1;
+        let rhs = parse_product(tokens, pos)?;
+        lhs = Expr::Binary(*op, Box::new(lhs), Box::new(rhs));
+    }
+
+    Ok(lhs)
+}
+
+fn parse_product(tokens: &[Token], pos: &mut usize) -> Result<Expr, String> {
+    let mut lhs = parse_atom(tokens, pos)?;
+
+    while let Some(Token::Op(op @ ('*' | '/'))) = tokens.get(*pos) {
+        *pos += 1;
+        let rhs = parse_atom(tokens, pos)?;
+        lhs = Expr::Binary(*op, Box::new(lhs), Box::new(rhs));
+    }
+
+    Ok(lhs)
+}
+
+fn parse_atom(tokens: &[Token], pos: &mut usize) -> Result<Expr, String> {
+    let token = tokens
+        .get(*pos)
+        .ok_or("expression ended unexpectedly")?
+        .clone();
+    *pos += 1;
+
+    match token {
+        Token::Number(n) => Ok(Expr::Number(n)),
+        Token::Ident(name) => Ok(Expr::Variable(name)),
+        Token::Op('-') => Ok(Expr::Neg(Box::new(parse_atom(tokens, pos)?))),
+        Token::Op('(') => {
+            let inner = parse_sum(tokens, pos)?;
+
+            if tokens.get(*pos) != Some(&Token::Op(')')) {
+                return Err("missing closing parenthesis".to_string());
+            }
+            *pos += 1;
+
+            Ok(inner)
+        }
+        Token::Op(op) => Err(format!("unexpected {:?}", op)),
+    }
+}
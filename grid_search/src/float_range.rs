@@ -1,31 +1,118 @@
+#[derive(Clone)]
 pub struct FloatRange {
-    pub current: f64,
+    pub start: f64,
     pub end: f64,
     pub step: f64,
+    index: usize,
+    len: usize,
+    // whether the last value is `end` itself, however far it is from the step before
+    end_forced: bool,
 }
 
 impl Iterator for FloatRange {
     type Item = f64;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if (self.step > 0. && self.current >= self.end)
-            || (self.step < 0. && self.current <= self.end)
-        {
+        if self.index >= self.len {
             return None;
         }
 
-        let res = self.current;
-        self.current += self.step;
+        // computed from the index rather than accumulated so the values can
+        // never drift away from what `range_len` counted
+        let res = self.value_at(self.index);
+        self.index += 1;
         Some(res)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.len - self.index;
+        (remaining, Some(remaining))
+    }
 }
 
+impl ExactSizeIterator for FloatRange {}
+
 impl FloatRange {
     pub fn new(start: f64, end: f64, step: f64) -> FloatRange {
         FloatRange {
-            current: start,
+            start,
+            end,
+            step,
+            index: 0,
+            len: range_len(start, end, step),
+            end_forced: false,
+        }
+    }
+
+    // the values `new` steps through, then `end` even when it isn't a whole
+    // number of steps from start
+    pub fn with_end(start: f64, end: f64, step: f64) -> FloatRange {
+        FloatRange {
+            start,
             end,
             step,
+            index: 0,
+            len: range_len(start, end, step) + 1,
+            end_forced: true,
+        }
+    }
+
+    // `count` values from start to end inclusive
+    pub fn with_count(start: f64, end: f64, count: usize) -> FloatRange {
+        let step = if count > 1 {
+            (end - start) / (count - 1) as f64
+        } else {
+            0.
+        };
+
+        FloatRange {
+            start,
+            end,
+            step,
+            index: 0,
+            len: count,
+            end_forced: false,
+        }
+    }
+
+    pub fn value_count(&self) -> usize {
+        self.len
+    }
+
+    pub fn value_at(&self, index: usize) -> f64 {
+        if self.end_forced && index + 1 == self.len {
+            return self.end;
+        }
+
+        self.start + index as f64 * self.step
+    }
+
+    // every value in the range, regardless of how far it has been iterated
+    pub fn collect_values(&self) -> Vec<f64> {
+        FloatRange {
+            index: 0,
+            ..self.clone()
         }
+        .collect()
     }
 }
+
+// relative to the number of steps, far below anything a step could be meant to be
+const STEP_TOLERANCE: f64 = 1e-9;
+
+pub fn range_len(start: f64, end: f64, step: f64) -> usize {
+    let steps = (end - start) / step;
+
+    if step == 0. || steps.is_nan() || steps <= 0. {
+        return 0;
+    }
+
+    // an end that's a whole number of steps away is excluded, even when the
+    // division lands just above that number, e.g. (0.4 - 0.1) / 0.1
+    let nearest = steps.round();
+    if (steps - nearest).abs() <= STEP_TOLERANCE * nearest.max(1.) {
+        return nearest as usize;
+    }
+
+    steps.ceil() as usize
+}
@@ -0,0 +1,43 @@
+use std::{path::Path, process::Command};
+
+// the commit the script was last checked out at, with -dirty on the end when
+// it has changes that aren't committed. Err when it isn't in a git repo
+pub fn script_commit(script: &str) -> Result<String, String> {
+    let path = Path::new(script);
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let file_name = path.file_name().ok_or("the script has no file name")?;
+
+    let commit = git(dir, &["rev-parse", "HEAD"])?;
+    let status = git(
+        dir,
+        &["status", "--porcelain", "--", &file_name.to_string_lossy()],
+    )?;
+
+    Ok(match status.is_empty() {
+        true => commit,
+        false => format!("{}-dirty", commit),
+    })
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|why| format!("couldn't run git: {}", why))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed in {}: {}",
+            args[0],
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
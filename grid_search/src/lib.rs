@@ -1,151 +1,522 @@
+pub mod analysis;
+pub mod archive;
+#[cfg(feature = "cli")]
 pub mod args;
+pub mod backtester;
+pub mod budget;
 pub mod config;
+pub mod cursor;
+pub mod disk;
+pub mod evaluation;
+pub mod expr;
 pub mod float_range;
+pub mod git;
+pub mod log_writer;
+pub mod manifest;
+pub mod plot;
+pub mod pool;
+pub mod progress;
+pub mod refine;
+pub mod results_csv;
 pub mod run;
+pub mod sampling;
+pub mod state;
+pub mod timing;
+#[cfg(feature = "cli")]
+pub mod watch;
 
 use std::{
+    collections::{BTreeMap, HashMap},
     fs::{self, File},
-    io::Write,
+    io::{Read, Write},
     option::Option,
     path::{Path, PathBuf},
 };
 
-use config::VariableConfig;
+use config::{Config, ProfitMatch, ROUND_VARIABLE, Strategy, VariableConfig};
+use expr::parse_expr;
+use flate2::read::GzDecoder;
 use float_range::FloatRange;
 use regex::Regex;
 
-pub fn create_or_clean_logs_dir(path: &Path, num_of_combinations: usize) {
+pub fn create_or_clean_logs_dir(cfg: &Config, num_of_combinations: usize) {
+    let path = Path::new(&cfg.logs_dir);
+
     if path.exists() {
         fs::remove_dir_all(path).unwrap();
     }
 
-    let dir_limit = ((num_of_combinations + 99) / 100) * 100;
-
-    for i in (0..dir_limit).step_by(100) {
-        let start = i;
-        let end = i + 99;
-
-        let subdir = path.join(format!("{}-{}", start, end));
+    for i in (0..num_of_combinations).step_by(cfg.bucket_size) {
+        let subdir = get_bucket_dir(i, cfg);
 
         fs::create_dir_all(subdir.join("logs")).unwrap();
         fs::create_dir_all(subdir.join("scripts")).unwrap();
     }
 }
 
-pub fn get_profit(output: &str) -> Option<f64> {
-    let re = Regex::new(r"Total profit:\s*([\d,]+)").unwrap();
+pub fn get_bucket_dir(i: usize, cfg: &Config) -> PathBuf {
+    let idx_range_start = (i / cfg.bucket_size) * cfg.bucket_size;
+    let idx_range_end = idx_range_start + cfg.bucket_size - 1;
 
-    re.captures(output).and_then(|caps| {
-        caps.get(1)
-            .map(|m| {
-                let clean = m.as_str().replace(",", "");
-                clean.parse::<f64>().ok()
-            })
-            .flatten()
-    })
+    Path::new(&cfg.logs_dir).join(format!("{}-{}", idx_range_start, idx_range_end))
 }
 
-pub fn get_log_path(i: usize, logs_dir: &str) -> PathBuf {
-    let logs_dir = Path::new(logs_dir);
+pub fn get_profit(output: &str, profit_pattern: &str, profit_match: ProfitMatch) -> Option<f64> {
+    let re = Regex::new(profit_pattern).unwrap();
 
-    let idx_range_start = (i / 100) * 100;
-    let idx_range_end = idx_range_start + 99;
+    let mut profits = re.captures_iter(output).filter_map(|caps| {
+        caps.get(1).and_then(|m| {
+            let clean = m.as_str().replace(",", "");
+            clean.parse::<f64>().ok()
+        })
+    });
 
-    let log_subdir = format!("{}-{}", idx_range_start, idx_range_end);
-    let log_fp_leaf = format!("log_{}.txt", i);
-
-    logs_dir.join(log_subdir).join("logs").join(log_fp_leaf)
+    match profit_match {
+        ProfitMatch::First => profits.next(),
+        ProfitMatch::Last => profits.last(),
+        ProfitMatch::Max => profits.reduce(f64::max),
+        ProfitMatch::Sum => profits.reduce(|a, b| a + b),
+    }
 }
 
-pub fn create_file(contents: &str, path: &PathBuf) {
-    let display = path.display();
-    let mut file = match File::create(&path) {
-        Ok(file) => file,
-        Err(why) => panic!("couldn't create {}: {}", display, why),
+// what's compared and stored for a parsed profit, negated first and then rounded
+pub fn normalize_profit(profit: f64, cfg: &Config) -> f64 {
+    // subtracting from zero keeps a zero profit from printing as -0
+    let profit = if cfg.negate_profit {
+        0. - profit
+    } else {
+        profit
     };
 
-    if let Err(why) = file.write_all(contents.as_bytes()) {
-        panic!("couldn't write to {}: {}", display, why);
+    round_profit(profit, cfg.round_profit_dp)
+}
+
+// the keys an objective can use besides the variables and derived constants
+pub const OBJECTIVE_METRICS: [&str; 2] = ["profit", "duration_secs"];
+
+pub fn objective_value(
+    objective: &str,
+    profit: f64,
+    duration_secs: f64,
+    parameters: &BTreeMap<String, f64>,
+) -> Result<f64, String> {
+    let mut values = parameters.clone().into_iter().collect::<HashMap<_, _>>();
+    values.insert(String::from("profit"), profit);
+    values.insert(String::from("duration_secs"), duration_secs);
+
+    parse_expr(objective)?.eval(&values)
+}
+
+pub fn round_profit(profit: f64, decimal_places: Option<usize>) -> f64 {
+    match decimal_places {
+        Some(dp) => {
+            let scale = 10f64.powi(dp as i32);
+            (profit * scale).round() / scale
+        }
+        None => profit,
     }
 }
 
-pub fn get_script_path(i: usize, logs_dir: &str) -> PathBuf {
-    let logs_dir = Path::new(logs_dir);
+pub fn get_log_path(i: usize, cfg: &Config) -> PathBuf {
+    let log_fp_leaf = format!("log_{}.txt", i);
 
-    let idx_range_start = (i / 100) * 100;
-    let idx_range_end = idx_range_start + 99;
+    get_bucket_dir(i, cfg).join("logs").join(log_fp_leaf)
+}
+
+pub fn create_file(contents: &str, path: &PathBuf) -> Result<(), String> {
+    let mut file = File::create(path).map_err(|why| disk::describe_write_error(path, &why))?;
+
+    file.write_all(contents.as_bytes())
+        .map_err(|why| disk::describe_write_error(path, &why))
+}
 
-    let script_subdir = format!("{}-{}", idx_range_start, idx_range_end);
+pub fn get_script_path(i: usize, cfg: &Config) -> PathBuf {
     let script_fp_leaf = format!("script_{}.py", i);
 
-    logs_dir
-        .join(script_subdir)
-        .join("scripts")
-        .join(script_fp_leaf)
+    get_bucket_dir(i, cfg).join("scripts").join(script_fp_leaf)
+}
+
+pub fn get_manifest_path(i: usize, cfg: &Config) -> PathBuf {
+    get_bucket_dir(i, cfg).join("index.json")
+}
+
+// the constants without a swept round, which is passed to the backtester
+// instead of being written into the script
+pub fn script_constants(constants: &str) -> String {
+    constants
+        .lines()
+        .filter(|line| {
+            line.split_once('=')
+                .is_none_or(|(name, _)| name.trim() != ROUND_VARIABLE)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
-pub fn replace_constants(script_contents: &str, new_constants: &str) -> String {
-    let re = Regex::new(r"(?s)# start.*?# end").unwrap();
+pub fn replace_constants(
+    script_contents: &str,
+    new_constants: &str,
+    start_marker: &str,
+    end_marker: &str,
+) -> Result<String, String> {
+    Ok(Template::split(script_contents, start_marker, end_marker)?.fill(new_constants))
+}
 
-    re.replace(script_contents, format!("# start\n{}\n#end", new_constants))
-        .to_string()
+// a script split around its constants block once, so each combination only
+// has to join the parts back together around its own constants
+pub struct Template {
+    // everything up to and including the start marker
+    prefix: String,
+    // the end marker and everything after it
+    suffix: String,
 }
 
+impl Template {
+    // the block runs from the first start marker to the first end marker after it
+    pub fn split(
+        script_contents: &str,
+        start_marker: &str,
+        end_marker: &str,
+    ) -> Result<Template, String> {
+        let (start, end) = find_block(script_contents, start_marker, end_marker)?;
+
+        Ok(Template {
+            prefix: script_contents[..start].to_string(),
+            suffix: script_contents[end..].to_string(),
+        })
+    }
+
+    pub fn fill(&self, constants: &str) -> String {
+        let mut script =
+            String::with_capacity(self.prefix.len() + constants.len() + self.suffix.len() + 2);
+
+        script.push_str(&self.prefix);
+        script.push('\n');
+        script.push_str(constants);
+        script.push('\n');
+        script.push_str(&self.suffix);
+
+        script
+    }
+}
+
+// what's between the markers, the constants the template runs with as it is
+pub fn template_constants(
+    script_contents: &str,
+    start_marker: &str,
+    end_marker: &str,
+) -> Result<String, String> {
+    let (start, end) = find_block(script_contents, start_marker, end_marker)?;
+
+    Ok(script_contents[start..end].to_string())
+}
+
+// where the constants block starts, just after the start marker, and ends, at
+// the end marker
+fn find_block(
+    script_contents: &str,
+    start_marker: &str,
+    end_marker: &str,
+) -> Result<(usize, usize), String> {
+    let Some(start) = script_contents.find(start_marker) else {
+        return Err(format!("couldn't find the start marker {:?}", start_marker));
+    };
+    let after_start = start + start_marker.len();
+
+    let Some(end) = script_contents[after_start..].find(end_marker) else {
+        return Err(match script_contents.find(end_marker) {
+            Some(_) => format!(
+                "the end marker {:?} comes before the start marker {:?}",
+                end_marker, start_marker
+            ),
+            None => format!("couldn't find the end marker {:?}", end_marker),
+        });
+    };
+
+    Ok((after_start, after_start + end))
+}
+
+pub fn script_header(i: usize, constants: &str) -> String {
+    let parameters = constants
+        .lines()
+        .map(|line| line.split_whitespace().collect::<String>())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+
+    format!(
+        "# grid_search combination {}: {}\n",
+        i,
+        parameters.join(", ")
+    )
+}
+
+// gzipped files are decompressed, so a template can be kept as script.py.gz
 pub fn read_file(fp: &str) -> Result<String, Box<dyn std::error::Error>> {
     let path = Path::new(fp);
+
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        let mut contents = String::new();
+        GzDecoder::new(File::open(path)?).read_to_string(&mut contents)?;
+        return Ok(contents);
+    }
+
     let contents = fs::read_to_string(path)?;
     Ok(contents)
 }
 
-pub fn get_constant_strings(vars: &Vec<VariableConfig>) -> Vec<String> {
-    let loop_ranges = generate_loops(&vars);
+pub fn tail_lines(contents: &str, n: usize) -> String {
+    let lines = contents.lines().collect::<Vec<_>>();
+    let start = lines.len().saturating_sub(n);
+
+    lines[start..].join("\n")
+}
+
+pub fn get_constant_strings(cfg: &Config) -> Vec<String> {
+    match cfg.strategy {
+        Strategy::Grid => {
+            let loop_ranges = generate_loops(&cfg.variables);
+
+            generate_combinations(&loop_ranges, cfg)
+        }
+        Strategy::Lhs => {
+            let mut rng = sampling::new_rng(cfg.seed);
+
+            sampling::latin_hypercube(&cfg.variables, cfg.n_samples, &mut rng)
+                .iter()
+                .map(|values| format_combination(values, cfg))
+                .collect()
+        }
+    }
+}
+
+// saturates at u128::MAX for grids too large to ever be enumerated
+pub fn count_combinations(cfg: &Config) -> u128 {
+    if cfg.strategy == Strategy::Lhs {
+        return cfg.n_samples as u128;
+    }
+
+    let ranges = generate_loops(&cfg.variables);
+
+    if is_conditional(cfg) {
+        return count_conditional(
+            &ranges,
+            &cfg.variables,
+            &nesting(cfg),
+            0,
+            &mut vec![0.; ranges.len()],
+        );
+    }
 
-    generate_combinations(&loop_ranges, &vars)
+    ranges.iter().fold(1u128, |acc, range| {
+        acc.saturating_mul(range.value_count() as u128)
+    })
 }
 
-fn generate_loops(vars: &Vec<VariableConfig>) -> Vec<FloatRange> {
-    let mut res = Vec::new();
+fn is_conditional(cfg: &Config) -> bool {
+    cfg.variables.iter().any(|var| var.active_when.is_some())
+}
 
-    for var in vars {
-        res.push(FloatRange::new(var.start, var.end, var.step));
+// only the variables others are conditional on are walked value by value,
+// the rest just multiply by how many values they take
+fn count_conditional(
+    ranges: &[FloatRange],
+    vars: &[VariableConfig],
+    nesting: &[usize],
+    depth: usize,
+    current: &mut Vec<f64>,
+) -> u128 {
+    let Some(&index) = nesting.get(depth) else {
+        return 1;
+    };
+
+    let values = active_values(ranges, vars, index, current);
+    let controls = vars.iter().any(|var| {
+        var.active_when
+            .as_ref()
+            .is_some_and(|condition| condition.variable == vars[index].name)
+    });
+
+    if !controls {
+        current[index] = values.first().copied().unwrap_or_default();
+        let rest = count_conditional(ranges, vars, nesting, depth + 1, current);
+
+        return (values.len() as u128).saturating_mul(rest);
     }
 
-    res
+    values.iter().fold(0u128, |acc, value| {
+        current[index] = *value;
+        let rest = count_conditional(ranges, vars, nesting, depth + 1, current);
+
+        acc.saturating_add(rest)
+    })
 }
 
-fn generate_combinations(ranges: &[FloatRange], vars: &Vec<VariableConfig>) -> Vec<String> {
+// the values variable `index` takes given the ones nested outside it, just its
+// first while it's inactive
+fn active_values(
+    ranges: &[FloatRange],
+    vars: &[VariableConfig],
+    index: usize,
+    current: &[f64],
+) -> Vec<f64> {
+    let mut values = ranges[index].collect_values();
+
+    if let Some(condition) = &vars[index].active_when {
+        let controller = vars
+            .iter()
+            .position(|var| var.name == condition.variable)
+            .expect("active_when is checked by validate_config");
+
+        if !condition.matches(current[controller]) {
+            values.truncate(1);
+        }
+    }
+
+    values
+}
+
+// the combinations from `start` onwards, in the same order as
+// `get_constant_strings`, without generating the ones before it
+pub fn combinations_from(cfg: &Config, start: usize) -> Box<dyn Iterator<Item = String> + '_> {
+    // a conditional grid isn't a plain cross product an index can be decoded from
+    if cfg.strategy != Strategy::Grid || is_conditional(cfg) {
+        return Box::new(get_constant_strings(cfg).into_iter().skip(start));
+    }
+
+    let ranges = generate_loops(&cfg.variables);
+    let nesting = nesting(cfg);
+    let total = usize::try_from(count_combinations(cfg)).unwrap_or(usize::MAX);
+
+    Box::new(
+        (start..total)
+            .map(move |index| format_combination(&grid_point(&ranges, &nesting, index), cfg)),
+    )
+}
+
+// decodes an index into one value per variable, in declared order, the
+// innermost variable changing fastest as it does in the nested loops
+fn grid_point(ranges: &[FloatRange], nesting: &[usize], mut index: usize) -> Vec<f64> {
+    let mut values = vec![0.; ranges.len()];
+
+    for &var in nesting.iter().rev() {
+        let range = &ranges[var];
+
+        values[var] = range.value_at(index % range.value_count());
+        index /= range.value_count();
+    }
+
+    values
+}
+
+pub fn values_for(var: &VariableConfig) -> Vec<f64> {
+    range_for(var).collect_values()
+}
+
+fn range_for(var: &VariableConfig) -> FloatRange {
+    match (var.count, var.force_endpoints) {
+        (Some(count), _) => FloatRange::with_count(var.start, var.end, count),
+        (None, true) => FloatRange::with_end(var.start, var.end, var.step.unwrap_or_default()),
+        (None, false) => FloatRange::new(var.start, var.end, var.step.unwrap_or_default()),
+    }
+}
+
+fn generate_loops(vars: &[VariableConfig]) -> Vec<FloatRange> {
+    vars.iter().map(range_for).collect()
+}
+
+fn generate_combinations(ranges: &[FloatRange], cfg: &Config) -> Vec<String> {
+    // `current` holds the values in declared order, whatever order they're set in
     fn helper(
         ranges: &[FloatRange],
-        index: usize,
+        nesting: &[usize],
+        depth: usize,
         current: &mut Vec<f64>,
         output: &mut Vec<String>,
-        vars: &Vec<VariableConfig>,
+        cfg: &Config,
     ) {
-        if index == ranges.len() {
-            let combo = current
-                .iter()
-                .enumerate()
-                .map(|(i, v)| format!("{} = {:.3}", vars[i].name, v))
-                .collect::<Vec<_>>()
-                .join("\n");
-            output.push(combo);
+        let Some(&index) = nesting.get(depth) else {
+            output.push(format_combination(current, cfg));
             return;
-        }
-
-        let range = FloatRange {
-            current: ranges[index].current,
-            end: ranges[index].end,
-            step: ranges[index].step,
         };
 
-        for val in range {
-            current.push(val);
-            helper(ranges, index + 1, current, output, &vars);
-            current.pop();
+        for val in active_values(ranges, &cfg.variables, index, current) {
+            current[index] = val;
+            helper(ranges, nesting, depth + 1, current, output, cfg);
         }
     }
 
     let mut output = Vec::new();
-    helper(ranges, 0, &mut Vec::new(), &mut output, &vars);
+    helper(
+        ranges,
+        &nesting(cfg),
+        0,
+        &mut vec![0.; ranges.len()],
+        &mut output,
+        cfg,
+    );
     output
 }
+
+// variable indices from the outermost loop to the innermost, which changes
+// fastest. the declared order unless `order` says otherwise
+fn nesting(cfg: &Config) -> Vec<usize> {
+    if cfg.order.is_empty() {
+        return (0..cfg.variables.len()).collect();
+    }
+
+    cfg.order
+        .iter()
+        .map(|name| {
+            cfg.variables
+                .iter()
+                .position(|var| &var.name == name)
+                .expect("order is checked by validate_config")
+        })
+        .collect()
+}
+
+// the constants block the sweep injects for these parameters, each variable
+// formatted as configured and the derived constants worked out from them.
+// other keys in `parameters`, like derived constants, are ignored
+pub fn format_constants(
+    parameters: &BTreeMap<String, f64>,
+    cfg: &Config,
+) -> Result<String, String> {
+    let values = cfg
+        .variables
+        .iter()
+        .map(|var| {
+            parameters
+                .get(&var.name)
+                .copied()
+                .ok_or_else(|| format!("no value for {}", var.name))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(format_combination(&values, cfg))
+}
+
+fn format_combination(values: &[f64], cfg: &Config) -> String {
+    let mut lines = Vec::new();
+    let mut known = HashMap::new();
+
+    for (v, var) in values.iter().zip(&cfg.variables) {
+        lines.push(format!("{} = {}", var.name, var.format_value(*v)));
+        known.insert(var.name.clone(), *v);
+    }
+
+    for derived in &cfg.derived {
+        let value = parse_expr(&derived.expr)
+            .and_then(|expr| expr.eval(&known))
+            .expect("derived expressions are checked by validate_config");
+
+        lines.push(format!(
+            "{} = {}",
+            derived.name,
+            derived.format_value(value)
+        ));
+        known.insert(derived.name.clone(), value);
+    }
+
+    lines.join("\n")
+}
@@ -0,0 +1,105 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, SyncSender},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{config::Config, create_file};
+
+// where workers send finished logs, either straight to disk or to a writer
+// thread so they don't wait on slow storage
+pub struct LogSink {
+    buffer: Option<SyncSender<(PathBuf, String)>>,
+    // what's been handed over so far, written or not
+    bytes: AtomicU64,
+    logs: AtomicUsize,
+}
+
+impl LogSink {
+    // the handle is only there for a buffered sink, and finishes once every
+    // log sent to it has been written
+    pub fn new(cfg: &Config) -> (LogSink, Option<JoinHandle<Result<(), String>>>) {
+        let mut sink = LogSink {
+            buffer: None,
+            bytes: AtomicU64::new(0),
+            logs: AtomicUsize::new(0),
+        };
+
+        if cfg.write_buffer == 0 {
+            return (sink, None);
+        }
+
+        let (tx, rx) = mpsc::sync_channel(cfg.write_buffer);
+        let batch = cfg.write_buffer;
+        let interval = Duration::from_millis(cfg.flush_interval_ms);
+
+        let handle = thread::spawn(move || write_logs(rx, batch, interval));
+        sink.buffer = Some(tx);
+
+        (sink, Some(handle))
+    }
+
+    pub fn write(&self, contents: String, path: PathBuf) -> Result<(), String> {
+        self.bytes
+            .fetch_add(contents.len() as u64, Ordering::Relaxed);
+        self.logs.fetch_add(1, Ordering::Relaxed);
+
+        match &self.buffer {
+            None => create_file(&contents, &path),
+            Some(tx) => tx
+                .send((path, contents))
+                .map_err(|_| String::from("the log writer stopped")),
+        }
+    }
+
+    // the average size of the logs handed over, the same whether or not a
+    // buffered sink has got round to writing them yet
+    pub fn mean_log_bytes(&self) -> u64 {
+        match self.logs.load(Ordering::Relaxed) {
+            0 => 0,
+            logs => self.bytes.load(Ordering::Relaxed) / logs as u64,
+        }
+    }
+}
+
+// writes in batches of `batch`, or whatever has arrived once `interval` has
+// passed since the last write
+fn write_logs(
+    rx: Receiver<(PathBuf, String)>,
+    batch: usize,
+    interval: Duration,
+) -> Result<(), String> {
+    let mut pending = Vec::with_capacity(batch);
+    let mut last_flush = Instant::now();
+
+    loop {
+        let wait = interval.saturating_sub(last_flush.elapsed());
+
+        match rx.recv_timeout(wait) {
+            Ok(log) => {
+                pending.push(log);
+
+                if pending.len() < batch && last_flush.elapsed() < interval {
+                    continue;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return flush(&mut pending),
+        }
+
+        flush(&mut pending)?;
+        last_flush = Instant::now();
+    }
+}
+
+fn flush(pending: &mut Vec<(PathBuf, String)>) -> Result<(), String> {
+    for (path, contents) in pending.drain(..) {
+        create_file(&contents, &path)?;
+    }
+
+    Ok(())
+}
@@ -1,18 +1,508 @@
 use grid_search::{
-    args::get_opts, config::parse_config, create_or_clean_logs_dir, get_constant_strings,
-    run::run_all,
+    archive::archive_sweep,
+    args::{Command, Options, get_opts},
+    backtester::find_backtester,
+    combinations_from,
+    config::{Config, ScriptDelivery, parse_config, read_ranges_csv, validate_config},
+    count_combinations, create_or_clean_logs_dir,
+    disk::format_bytes,
+    get_profit,
+    git::script_commit,
+    normalize_profit, read_file,
+    refine::{REFINE_FRACTION, refine_variables},
+    run::{
+        Backtester, Best, backtester_args, bench_with, delivered_script_path, merge_slices, run_all,
+    },
+    state::load_state,
+    timing::{command_line, format_secs, load_timing},
+    values_for,
+    watch::watch_config,
 };
 
-use std::path::Path;
+use std::{
+    fs,
+    path::{Component, Path},
+    process,
+};
 
 fn main() {
     let opts = get_opts();
 
-    let cfg = parse_config(&opts.config).unwrap();
+    match &opts.command {
+        Some(Command::ParseTest { input }) => parse_test(&opts, input),
+        Some(Command::Merge { inputs, out }) => merge(&opts, inputs, out.as_deref()),
+        Some(Command::Archive { out, logs }) => archive(&opts, out, *logs),
+        Some(Command::Bench {
+            combos,
+            thread_counts,
+        }) => bench(&opts, *combos, thread_counts),
+        None => run_sweeps(&opts),
+    }
+}
+
+fn parse_test(opts: &Options, input: &str) {
+    let output = read_file(input).unwrap();
+
+    for config_path in &opts.config {
+        let cfg = parse_config(config_path).unwrap();
+
+        println!("{} (profit_pattern {:?}):", config_path, cfg.profit_pattern);
+
+        match get_profit(&output, &cfg.profit_regex(), cfg.profit_match)
+            .map(|profit| normalize_profit(profit, &cfg))
+        {
+            Some(profit) => println!("  profit: {}", profit),
+            None => println!("  no profit found"),
+        }
+    }
+}
+
+fn merge(opts: &Options, inputs: &[String], out: Option<&str>) {
+    // the slices all ran the same config, so only the first is needed
+    let mut cfg = parse_config(&opts.config[0]).unwrap();
+
+    // the combined csv is written from scratch, with best.json next to it
+    // unless the config keeps its state somewhere
+    if let Some(out) = out {
+        cfg.results_csv = Some(out.to_string());
+        cfg.append_results = false;
+        cfg.state_file.get_or_insert_with(|| {
+            Path::new(out)
+                .with_file_name("best.json")
+                .to_string_lossy()
+                .into_owned()
+        });
+    }
+
+    if opts.tag.is_some() {
+        cfg.run_tag = opts.tag.clone();
+    }
+
+    if let Err(why) = merge_slices(inputs, &cfg) {
+        eprintln!("merge failed: {}", why);
+        process::exit(1);
+    }
+}
+
+fn archive(opts: &Options, out: &str, include_logs: bool) {
+    let cfg = parse_config(&opts.config[0]).unwrap();
+
+    match archive_sweep(&cfg, Path::new(out), include_logs) {
+        Ok(files) => println!(
+            "Archived {} files from {} into {}",
+            files, cfg.logs_dir, out
+        ),
+        Err(why) => {
+            eprintln!("archive failed: {}", why);
+            process::exit(1);
+        }
+    }
+}
+
+fn bench(opts: &Options, combos: usize, thread_counts: &[usize]) {
+    let cfg = parse_config(&opts.config[0]).unwrap();
+
+    let backtester = match find_backtester(&cfg.backtester_search_paths) {
+        Ok(backtester) => backtester,
+        Err(why) => {
+            eprintln!("bench failed: {}", why);
+            process::exit(1);
+        }
+    };
+
+    // doubling from 1, and the most threads there are if that isn't a power of 2
+    let thread_counts = match thread_counts.is_empty() {
+        true => {
+            let max = opts.threads.max(1) as usize;
+            let mut counts = (0..)
+                .map(|power| 1 << power)
+                .take_while(|threads| *threads < max)
+                .collect::<Vec<_>>();
+            counts.push(max);
+            counts
+        }
+        false => thread_counts.to_vec(),
+    };
+
+    let constants = combinations_from(&cfg, 0).next().unwrap_or_default();
+    fs::create_dir_all(&cfg.logs_dir).unwrap();
+    let runner = Backtester { path: backtester };
+
+    println!(
+        "Timing {} runs of combination 0 with {}",
+        combos,
+        runner.path.display()
+    );
+
+    for threads in thread_counts {
+        match bench_with(&constants, &cfg, threads.max(1), combos, &runner) {
+            Ok(result) => println!(
+                "  {:>3} threads: {:.2} runs/s, {:.3}s a run",
+                result.threads,
+                result.runs_per_sec(),
+                result.mean_run_secs
+            ),
+            Err(why) => {
+                eprintln!("bench failed: {}", why);
+                process::exit(1);
+            }
+        }
+    }
+}
+
+fn run_sweeps(opts: &Options) {
+    let batch = opts.config.len() > 1;
+    let names = batch_names(&opts.config);
+    let mut leaderboard: Vec<(String, Result<Option<Best>, String>)> = Vec::new();
+
+    // one config failing doesn't stop the rest of a batch
+    for (config_path, name) in opts.config.iter().zip(&names) {
+        let cfg = match prepare_config(opts, config_path, batch.then_some(name.as_str())) {
+            Ok(cfg) => cfg,
+            Err(why) => {
+                eprintln!("{} aborted: {}", config_path, why);
+                leaderboard.push((config_path.clone(), Err(why)));
+                continue;
+            }
+        };
+
+        if opts.explain {
+            explain(config_path, &cfg, opts.threads);
+            continue;
+        }
+
+        // resuming needs what the earlier run left in logs_dir
+        if !cfg.resume {
+            let total = usize::try_from(count_combinations(&cfg)).unwrap_or(usize::MAX);
+            create_or_clean_logs_dir(&cfg, total);
+        }
+
+        let best = run_all(&cfg, opts.threads as usize).map_err(|why| {
+            eprintln!("{} aborted: {}", config_path, why);
+            why.to_string()
+        });
+
+        leaderboard.push((config_path.clone(), best));
+    }
+
+    if batch {
+        print_leaderboard(&mut leaderboard);
+    }
+
+    if leaderboard.iter().any(|(_, best)| best.is_err()) {
+        process::exit(1);
+    }
+}
+
+// the config at `config_path` with the command line applied, `batch_name`
+// being where its outputs go inside logs_dir when it's one of several
+fn prepare_config(
+    opts: &Options,
+    config_path: &str,
+    batch_name: Option<&str>,
+) -> Result<Config, String> {
+    let mut cfg = match opts.watch {
+        true => watch_config(config_path),
+        false => parse_config(config_path),
+    }
+    .map_err(|why| why.to_string())?;
+
+    if let Some(ranges_csv) = &opts.ranges_csv {
+        cfg.variables = read_ranges_csv(ranges_csv).map_err(|why| why.to_string())?;
+    }
+
+    if let Some(name) = batch_name {
+        cfg.logs_dir = Path::new(&cfg.logs_dir)
+            .join(name)
+            .to_string_lossy()
+            .into_owned();
+        cfg.state_file = cfg
+            .state_file
+            .as_deref()
+            .map(|state_file| batch_state_file(&cfg.logs_dir, state_file));
+        println!("Running {} into {}", config_path, cfg.logs_dir);
+    }
+
+    if opts.no_scripts && cfg.script_delivery == ScriptDelivery::Disk {
+        cfg.script_delivery = ScriptDelivery::Ram;
+    }
+
+    if opts.continue_on_error {
+        cfg.continue_on_error = true;
+    }
+
+    if opts.script_header {
+        cfg.script_header = true;
+    }
+
+    if opts.baseline {
+        cfg.baseline = true;
+    }
+
+    if opts.note.is_some() {
+        cfg.note = opts.note.clone();
+    }
+
+    if let Some(progress) = opts.progress {
+        cfg.progress = progress;
+    }
+
+    if opts.append {
+        cfg.append_results = true;
+    }
+
+    if opts.tag.is_some() {
+        cfg.run_tag = opts.tag.clone();
+    }
+
+    if let Some(delimiter) = opts.csv_delimiter {
+        cfg.csv_delimiter = delimiter;
+    }
+
+    if let Some(quote) = opts.csv_quote {
+        cfg.csv_quote = quote;
+    }
+
+    if opts.allow_empty {
+        cfg.allow_empty = true;
+    }
+
+    if opts.resume {
+        cfg.resume = true;
+    }
+
+    if opts.max_runs.is_some() {
+        cfg.max_runs = opts.max_runs;
+    }
+
+    if opts.min_successes.is_some() {
+        cfg.min_successes = opts.min_successes;
+    }
+
+    if opts.min_success_rate.is_some() {
+        cfg.min_success_rate = opts.min_success_rate;
+    }
+
+    if opts.events_file.is_some() {
+        cfg.events_file = opts.events_file.clone();
+    }
+
+    if opts.seed.is_some() {
+        cfg.seed = opts.seed;
+    }
+
+    if let Some(slice) = opts.shard.or(opts.index_range) {
+        cfg.slice = Some(slice);
+    }
+
+    if let Some(slice) = cfg.slice {
+        cfg.logs_dir = Path::new(&cfg.logs_dir)
+            .join(slice.tag())
+            .to_string_lossy()
+            .into_owned();
+        cfg.state_file = cfg
+            .state_file
+            .as_deref()
+            .map(|state_file| batch_state_file(&cfg.logs_dir, state_file));
+        println!("Running {} into {}", slice.tag(), cfg.logs_dir);
+    }
+
+    if opts.refine {
+        let Some(state_file) = &cfg.state_file else {
+            return Err(String::from("--refine needs a state_file"));
+        };
+
+        let refined = load_state(state_file)
+            .map_err(|why| why.to_string())
+            .and_then(|state| state.best.ok_or(String::from("it has no best evaluation")))
+            .and_then(|best| refine_variables(&cfg.variables, &best.parameters, REFINE_FRACTION));
+
+        cfg.variables =
+            refined.map_err(|why| format!("can't refine from {}: {}", state_file, why))?;
+    }
+
+    if opts.require_clean_git {
+        cfg.require_clean_git = true;
+    }
+
+    match script_commit(&cfg.script) {
+        Ok(commit) if cfg.require_clean_git && commit.ends_with("-dirty") => {
+            return Err(format!("{} has changes that aren't committed", cfg.script));
+        }
+        Ok(commit) => cfg.script_commit = Some(commit),
+        Err(why) if cfg.require_clean_git => return Err(why.to_string()),
+        Err(_) => {}
+    }
+
+    // the command line can set what the config file was checked for
+    validate_config(&cfg).map_err(|why| why.to_string())?;
+
+    Ok(cfg)
+}
+
+fn explain(config_path: &str, cfg: &Config, threads: u8) {
+    println!("{}:", config_path);
+
+    if let Some(note) = &cfg.note {
+        println!("  {}", note);
+    }
+
+    println!("  script {} for round {}", cfg.script, cfg.round);
+    println!(
+        "  {:?} strategy over {} threads into {}",
+        cfg.strategy, threads, cfg.logs_dir
+    );
+
+    for var in &cfg.variables {
+        let values = values_for(var);
+
+        match (values.first(), values.last()) {
+            (Some(first), Some(last)) => println!(
+                "  {}: {} values from {} to {}",
+                var.name,
+                values.len(),
+                var.format_value(*first),
+                var.format_value(*last)
+            ),
+            _ => println!("  {}: no values", var.name),
+        }
+
+        if let Some(condition) = &var.active_when {
+            println!(
+                "    only while {} is one of {:?}",
+                condition.variable, condition.values
+            );
+        }
+    }
+
+    for derived in &cfg.derived {
+        println!("  {} = {}", derived.name, derived.expr);
+    }
+
+    let total = count_combinations(cfg);
+    match cfg.repeats {
+        1 => println!("  {} combinations", total),
+        repeats => println!(
+            "  {} combinations, {} runs each ({} runs)",
+            total,
+            repeats,
+            total.saturating_mul(repeats as u128)
+        ),
+    }
+    println!(
+        "  {} buckets of up to {} combinations",
+        total.div_ceil(cfg.bucket_size as u128),
+        cfg.bucket_size
+    );
+
+    match find_backtester(&cfg.backtester_search_paths) {
+        Ok(backtester) => {
+            println!(
+                "  combination 0 runs: {} {}",
+                backtester.display(),
+                backtester_args(cfg, &delivered_script_path(0, cfg)).join(" ")
+            );
+
+            if let Some(timing) = cfg
+                .timing_cache
+                .as_deref()
+                .and_then(|path| load_timing(path, &command_line(&backtester, cfg)))
+            {
+                let runs = total.saturating_mul(cfg.repeats as u128) as f64;
+
+                println!(
+                    "  about {} at {:.2}s a run over {} threads (averaged over {} earlier runs)",
+                    format_secs(runs * timing.mean_secs / threads.max(1) as f64),
+                    timing.mean_secs,
+                    threads,
+                    timing.runs
+                );
+            }
+        }
+        Err(why) => println!("  {}", why),
+    }
+
+    // the same up front estimate the sweep checks free space against
+    match read_file(&cfg.script) {
+        Ok(template) => println!(
+            "  at least {} of scripts",
+            format_bytes((template.len() as u128 * total).min(u64::MAX as u128) as u64)
+        ),
+        Err(why) => println!("  couldn't read {}: {}", cfg.script, why),
+    }
+}
+
+// where each config in a batch writes inside its logs_dir, its file stem
+// unless another config shares it, then as much of its path as tells them apart
+fn batch_names(config_paths: &[String]) -> Vec<String> {
+    let paths = config_paths
+        .iter()
+        .map(|config_path| {
+            Path::new(config_path)
+                .with_extension("")
+                .components()
+                .filter_map(|component| match component {
+                    Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    paths
+        .iter()
+        .enumerate()
+        .map(|(i, path)| {
+            for len in 1..=path.len() {
+                let suffix = &path[path.len() - len..];
+                let shared = paths
+                    .iter()
+                    .enumerate()
+                    .any(|(j, other)| j != i && other.ends_with(suffix));
+
+                if !shared {
+                    return suffix.join("-");
+                }
+            }
+
+            // the same config given twice still runs into two directories
+            format!("{}-{}", path.join("-"), i)
+        })
+        .collect()
+}
+
+// each config in a batch, or slice of a sweep, keeps its state next to its own logs
+fn batch_state_file(logs_dir: &str, state_file: &str) -> String {
+    let file_name = Path::new(state_file)
+        .file_name()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| String::from("best.json"));
+
+    Path::new(logs_dir)
+        .join(file_name)
+        .to_string_lossy()
+        .into_owned()
+}
 
-    let constant_strings = get_constant_strings(&cfg.variables);
+fn print_leaderboard(leaderboard: &mut [(String, Result<Option<Best>, String>)]) {
+    // failed configs sort after the ones that found nothing
+    let rank = |best: &Result<Option<Best>, String>| match best {
+        Ok(Some(best)) => best.profit,
+        Ok(None) => f64::MIN,
+        Err(_) => f64::NEG_INFINITY,
+    };
+    leaderboard.sort_by(|(_, a), (_, b)| rank(b).total_cmp(&rank(a)));
 
-    create_or_clean_logs_dir(&Path::new(&cfg.logs_dir), constant_strings.len());
+    println!("\nLeaderboard:");
 
-    run_all(&constant_strings, &cfg, &opts).unwrap();
+    for (config_path, best) in leaderboard.iter() {
+        match best {
+            Ok(Some(best)) => println!(
+                "{}: {} (combination {})\n{}\n",
+                config_path, best.profit, best.evaluation.index, best.constants
+            ),
+            Ok(None) => println!("{}: no profit found\n", config_path),
+            Err(why) => println!("{}: failed, {}\n", config_path, why),
+        }
+    }
 }
@@ -0,0 +1,276 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    combinations_from,
+    config::{Config, ParseFailure},
+    create_file,
+    evaluation::{Evaluation, RunOutcome, parse_constants},
+    get_manifest_path, read_file,
+    run::SOFT_STOP_PREFIX,
+};
+
+// every bucket's evaluations, keyed by combination index
+pub fn write_manifests(
+    evaluations: &[Evaluation],
+    cfg: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut buckets: BTreeMap<PathBuf, BTreeMap<usize, &Evaluation>> = BTreeMap::new();
+
+    for evaluation in evaluations {
+        buckets
+            .entry(get_manifest_path(evaluation.index, cfg))
+            .or_default()
+            .insert(evaluation.index, evaluation);
+    }
+
+    for (manifest_path, manifest) in buckets {
+        create_file(&serde_json::to_string_pretty(&manifest)?, &manifest_path)?;
+
+        #[cfg(feature = "msgpack")]
+        if cfg.binary_manifests {
+            let binary_path = manifest_path.with_extension("msgpack");
+
+            fs::write(&binary_path, rmp_serde::to_vec_named(&manifest)?)
+                .map_err(|why| crate::disk::describe_write_error(&binary_path, &why))?;
+        }
+    }
+
+    Ok(())
+}
+
+// reads index.msgpack over index.json where a bucket has both
+pub fn load_manifests(logs_dir: &str) -> Result<Vec<Evaluation>, Box<dyn std::error::Error>> {
+    let mut evaluations = Vec::new();
+
+    for bucket in fs::read_dir(logs_dir)? {
+        // buckets holding only other slices' combinations have no manifest
+        if let Some(manifest) = load_manifest(&bucket?.path())? {
+            evaluations.extend(manifest.into_values());
+        }
+    }
+
+    Ok(evaluations)
+}
+
+// what finished before `next`, read from only the buckets starting before it
+pub fn load_manifests_before(
+    logs_dir: &str,
+    next: usize,
+) -> Result<Vec<Evaluation>, Box<dyn std::error::Error>> {
+    let mut evaluations = Vec::new();
+
+    for bucket in fs::read_dir(logs_dir)? {
+        let bucket = bucket?.path();
+        let bucket_start = bucket
+            .file_name()
+            .and_then(|name| name.to_str()?.split_once('-'))
+            .and_then(|(start, _)| start.parse::<usize>().ok());
+
+        if bucket_start.is_none_or(|bucket_start| bucket_start >= next) {
+            continue;
+        }
+
+        if let Some(manifest) = load_manifest(&bucket)? {
+            evaluations.extend(manifest.into_values().filter(|e| e.index < next));
+        }
+    }
+
+    Ok(evaluations)
+}
+
+fn load_manifest(
+    bucket: &Path,
+) -> Result<Option<BTreeMap<usize, Evaluation>>, Box<dyn std::error::Error>> {
+    #[cfg(feature = "msgpack")]
+    {
+        let binary_path = bucket.join("index.msgpack");
+
+        if binary_path.exists() {
+            return Ok(Some(rmp_serde::from_slice(&fs::read(&binary_path)?)?));
+        }
+    }
+
+    let manifest_path = bucket.join("index.json");
+
+    if !manifest_path.exists() {
+        return Ok(None);
+    }
+
+    Ok(Some(serde_json::from_str(&read_file(
+        &manifest_path.to_string_lossy(),
+    )?)?))
+}
+
+// a finished sweep's evaluations, in index order, without rerunning it. a
+// bucket's manifest is taken as is, and logs it doesn't cover, from a sweep
+// that was killed before writing it, are read back into evaluations. a log
+// that's unreadable or was cut off part way through is skipped with a warning
+pub fn load_evaluations(
+    logs_dir: &str,
+    cfg: &Config,
+) -> Result<Vec<Evaluation>, Box<dyn std::error::Error>> {
+    let mut evaluations = BTreeMap::new();
+    let mut logs = BTreeMap::new();
+
+    for bucket in fs::read_dir(logs_dir)? {
+        let bucket = bucket?.path();
+
+        if !bucket.is_dir() {
+            continue;
+        }
+
+        if let Some(manifest) = load_manifest(&bucket)? {
+            evaluations.extend(manifest);
+        }
+
+        let Ok(entries) = fs::read_dir(bucket.join("logs")) else {
+            continue;
+        };
+
+        for entry in entries {
+            let path = entry?.path();
+
+            if let Some(index) = log_index(&path) {
+                logs.insert(index, path);
+            }
+        }
+    }
+
+    logs.retain(|index, _| !evaluations.contains_key(index));
+
+    // the parameters are worked out from the index, in one pass since a
+    // sampled sweep can only generate its combinations from the start
+    let mut combinations = combinations_from(cfg, 0).enumerate();
+
+    for (index, path) in logs {
+        let Some((_, constants)) = combinations.find(|(i, _)| *i == index) else {
+            eprintln!(
+                "warning: {} is past the end of the sweep, skipping it",
+                path.display()
+            );
+            break;
+        };
+
+        match read_file(&path.to_string_lossy())
+            .map_err(|why| why.to_string())
+            .and_then(|log| parse_log(index, &log, parse_constants(&constants), cfg))
+        {
+            Ok(evaluation) => {
+                evaluations.insert(index, evaluation);
+            }
+            Err(why) => eprintln!("warning: skipping {}: {}", path.display(), why),
+        }
+    }
+
+    Ok(evaluations.into_values().collect())
+}
+
+// `log_{i}.txt`, gzipped or not
+fn log_index(path: &Path) -> Option<usize> {
+    let name = path.file_name()?.to_str()?;
+    let name = name.strip_suffix(".gz").unwrap_or(name);
+
+    name.strip_prefix("log_")?
+        .strip_suffix(".txt")?
+        .parse()
+        .ok()
+}
+
+// the inverse of how run_combination writes a log. the run's duration isn't
+// in it, so that's left at 0
+fn parse_log(
+    index: usize,
+    log: &str,
+    parameters: BTreeMap<String, f64>,
+    cfg: &Config,
+) -> Result<Evaluation, String> {
+    let mut evaluation = Evaluation {
+        index,
+        parameters,
+        profit: None,
+        outcome: RunOutcome::Success,
+        raw_profit: None,
+        baseline_delta: None,
+        profit_std: None,
+        duration_secs: 0.,
+        error: None,
+    };
+
+    // scored on what it flushed at the soft timeout, then logged as usual
+    let (log, soft_stop) = match log.strip_prefix(SOFT_STOP_PREFIX) {
+        Some(stopped) => {
+            let (reason, log) = stopped.split_once(".\n\n\n").ok_or("the log was cut off")?;
+
+            (log, Some(reason))
+        }
+        None => (log, None),
+    };
+
+    if let Some(stopped) = log.strip_prefix("Stopped, ") {
+        let reason = stopped.lines().next().unwrap_or_default();
+        let reason = reason.strip_suffix('.').unwrap_or(reason);
+
+        evaluation.outcome = match reason {
+            "skipped" => RunOutcome::Skipped,
+            _ => RunOutcome::Timeout,
+        };
+        evaluation.error = Some(reason.to_string());
+        return Ok(evaluation);
+    }
+
+    if log.starts_with("No profit found.") {
+        if !log.ends_with("No profit found.") {
+            return Err(String::from("the log was cut off"));
+        }
+
+        evaluation.outcome = RunOutcome::NoProfit;
+        evaluation.profit = match cfg.on_parse_failure {
+            ParseFailure::Zero => Some(0.),
+            ParseFailure::Skip | ParseFailure::Abort => None,
+        };
+        return Ok(evaluation);
+    }
+
+    // a finished log ends in its profit, then the objective's value if there is one
+    let mut lines = log.lines().rev();
+    let last = lines.next().unwrap_or_default();
+    let (profit_line, objective_line) = match last.strip_prefix("Objective: ") {
+        Some(objective) => (lines.next().unwrap_or_default(), Some(objective)),
+        None => (last, None),
+    };
+
+    let profit_line = profit_line
+        .strip_prefix("Profit: ")
+        .ok_or("the log doesn't end in a profit, it may have been cut off")?;
+
+    // a log under log_threshold keeps only the value that was compared
+    let raw_profit = parse_log_number(profit_line.split(' ').next().unwrap_or_default())?;
+    let objective = objective_line.map(parse_log_number).transpose()?;
+    let profit = objective.unwrap_or(raw_profit);
+
+    if !profit.is_finite() {
+        evaluation.outcome = RunOutcome::NonFinite;
+        evaluation.error = Some(format!("the profit was {}", profit));
+        return Ok(evaluation);
+    }
+
+    evaluation.profit = Some(profit);
+    evaluation.raw_profit = objective.map(|_| raw_profit);
+
+    if let Some(reason) = soft_stop {
+        evaluation.outcome = RunOutcome::SoftTimeout;
+        evaluation.error = Some(reason.to_string());
+    }
+
+    Ok(evaluation)
+}
+
+fn parse_log_number(value: &str) -> Result<f64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} isn't a number", value))
+}
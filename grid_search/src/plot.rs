@@ -0,0 +1,75 @@
+use std::collections::BTreeSet;
+
+use serde_json::{Value, json};
+
+use crate::{
+    config::VariableConfig,
+    evaluation::{Evaluation, RunOutcome},
+};
+
+// a vega-lite spec with the profits inline, a line for a sweep over one
+// variable and a heatmap for two. None for anything else, there's no
+// obvious picture of three or more
+pub fn vega_lite_spec(evaluations: &[Evaluation], vars: &[VariableConfig]) -> Option<Value> {
+    // a variable held at one value isn't part of the picture
+    let swept = vars
+        .iter()
+        .filter(|var| {
+            evaluations
+                .iter()
+                .filter_map(|e| e.parameters.get(&var.name))
+                .map(|v| v.to_bits())
+                .collect::<BTreeSet<_>>()
+                .len()
+                > 1
+        })
+        .collect::<Vec<_>>();
+
+    let values = evaluations
+        .iter()
+        .filter(|e| e.outcome == RunOutcome::Success)
+        .map(|e| {
+            let mut row = json!({ "profit": e.profit });
+            for var in &swept {
+                row[&var.name] = json!(e.parameters.get(&var.name));
+            }
+            row
+        })
+        .collect::<Vec<_>>();
+
+    let (mark, encoding) = match swept.as_slice() {
+        [x] => (
+            json!({ "type": "line", "point": true }),
+            json!({
+                "x": { "field": x.name, "type": "quantitative", "title": x.display_name() },
+                "y": { "field": "profit", "type": "quantitative" },
+            }),
+        ),
+        [x, y] => (
+            json!("rect"),
+            json!({
+                "x": { "field": x.name, "type": "ordinal", "title": x.display_name() },
+                "y": {
+                    "field": y.name,
+                    "type": "ordinal",
+                    "sort": "descending",
+                    "title": y.display_name(),
+                },
+                "color": { "field": "profit", "type": "quantitative" },
+                "tooltip": [
+                    { "field": x.name, "type": "quantitative", "title": x.display_name() },
+                    { "field": y.name, "type": "quantitative", "title": y.display_name() },
+                    { "field": "profit", "type": "quantitative" },
+                ],
+            }),
+        ),
+        _ => return None,
+    };
+
+    Some(json!({
+        "$schema": "https://vega.github.io/schema/vega-lite/v5.json",
+        "data": { "values": values },
+        "mark": mark,
+        "encoding": encoding,
+    }))
+}
@@ -0,0 +1,148 @@
+// Persistent backtester workers for `worker_mode: "pool"`.
+//
+// Each rayon thread starts one worker process (the backtester with
+// `worker_args`) the first time it needs one and keeps it for the rest of the
+// sweep. For every combination a single line of JSON, wrapped here, is
+// written to the worker's stdin:
+//
+//     {"index": 42, "parameters": {"x": 0.5}, "script": "logs/0-99/scripts/script_42.py",
+//      "round": 0, "warmup": null, "seed": null}
+//
+// The worker runs the script and writes the same output a normal backtester
+// run would (including the `Total profit` line) to stdout, followed by a line
+// containing only `grid_search:done`. Whatever the worker writes to stderr
+// while a combination is in flight is recorded as that combination's stderr.
+// A worker that exits or replies with garbage is replaced on the next
+// combination.
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    io::{BufRead, BufReader, Read, Write},
+    path::Path,
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use serde_json::json;
+
+use crate::{config::Config, run::WorkerError};
+
+pub const DONE_MARKER: &str = "grid_search:done";
+
+pub struct PoolWorker {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    stderr: Arc<Mutex<String>>,
+}
+
+thread_local! {
+    static WORKER: RefCell<Option<PoolWorker>> = const { RefCell::new(None) };
+}
+
+impl PoolWorker {
+    pub fn start(backtester: &Path, args: &[String]) -> Result<PoolWorker, WorkerError> {
+        let mut child = Command::new(backtester)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("failed to start pool worker: {}", e))?;
+
+        // stderr has to be drained continuously or a chatty worker would
+        // block on a full pipe
+        let stderr = Arc::new(Mutex::new(String::new()));
+        let drained = Arc::clone(&stderr);
+        let mut child_stderr = BufReader::new(child.stderr.take().unwrap());
+
+        thread::spawn(move || {
+            let mut line = String::new();
+
+            while child_stderr.read_line(&mut line).is_ok_and(|n| n > 0) {
+                drained.lock().unwrap().push_str(&line);
+                line.clear();
+            }
+
+            // keep draining if the worker wrote something that isn't utf8
+            let _ = child_stderr.read_to_end(&mut Vec::new());
+        });
+
+        Ok(PoolWorker {
+            stdin: child.stdin.take().unwrap(),
+            stdout: BufReader::new(child.stdout.take().unwrap()),
+            stderr,
+            child,
+        })
+    }
+
+    // returns the worker's stdout and stderr for this request
+    pub fn run(&mut self, request: &str) -> Result<(String, String), WorkerError> {
+        self.stderr.lock().unwrap().clear();
+
+        writeln!(self.stdin, "{}", request)?;
+        self.stdin.flush()?;
+
+        let mut output = String::new();
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+
+            if self.stdout.read_line(&mut line)? == 0 {
+                return Err("pool worker exited before finishing the combination".into());
+            }
+
+            if line.trim_end() == DONE_MARKER {
+                let stderr = std::mem::take(&mut *self.stderr.lock().unwrap());
+                return Ok((output, stderr));
+            }
+
+            output.push_str(&line);
+        }
+    }
+}
+
+impl Drop for PoolWorker {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// runs one combination on this thread's worker, starting it if needed
+pub fn run_in_pool(
+    backtester: &Path,
+    cfg: &Config,
+    index: usize,
+    parameters: &BTreeMap<String, f64>,
+    script_path: &Path,
+) -> Result<(String, String), WorkerError> {
+    let request = json!({
+        "index": index,
+        "parameters": parameters,
+        "script": script_path,
+        "round": cfg.round,
+        "warmup": cfg.warmup,
+        "seed": cfg.seed,
+    })
+    .to_string();
+
+    WORKER.with(|worker| {
+        let mut worker = worker.borrow_mut();
+
+        if worker.is_none() {
+            *worker = Some(PoolWorker::start(backtester, &cfg.worker_args)?);
+        }
+
+        let res = worker.as_mut().unwrap().run(&request);
+
+        if res.is_err() {
+            *worker = None;
+        }
+
+        res
+    })
+}
@@ -0,0 +1,61 @@
+use std::{
+    io::{IsTerminal, stderr},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::config::Progress;
+
+// counts finished combinations on stderr, redrawn in place on a terminal and
+// as a plain line every tenth of the way otherwise, so a log file or CI
+// output doesn't fill up with carriage returns
+pub struct ProgressLine {
+    total: usize,
+    animated: bool,
+    enabled: bool,
+    last_tenth: AtomicUsize,
+}
+
+impl ProgressLine {
+    pub fn new(mode: Progress, total: usize) -> ProgressLine {
+        let animated = match mode {
+            Progress::Always => true,
+            Progress::Auto => stderr().is_terminal(),
+            Progress::Never => false,
+        };
+
+        ProgressLine {
+            total,
+            animated,
+            enabled: mode != Progress::Never && total > 0,
+            last_tenth: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn update(&self, completed: usize) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.animated {
+            eprint!("\r{}/{} combinations", completed, self.total);
+            return;
+        }
+
+        let tenth = completed * 10 / self.total;
+        if self.last_tenth.fetch_max(tenth, Ordering::Relaxed) < tenth {
+            eprintln!(
+                "{}/{} combinations ({}%)",
+                completed,
+                self.total,
+                completed * 100 / self.total
+            );
+        }
+    }
+
+    // moves past the redrawn line so what follows starts on its own
+    pub fn finish(&self) {
+        if self.enabled && self.animated {
+            eprintln!();
+        }
+    }
+}
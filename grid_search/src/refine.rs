@@ -0,0 +1,48 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    config::{ROUND_VARIABLE, VariableConfig},
+    values_for,
+};
+
+// how much of a variable's original range the refined one spans either side
+// of the best value
+pub const REFINE_FRACTION: f64 = 0.1;
+
+// every variable narrowed to a range centred on its best value, clamped to
+// where it was swept before. the count is kept, made odd so an unclamped
+// range has the best value itself in the middle
+pub fn refine_variables(
+    variables: &[VariableConfig],
+    best: &BTreeMap<String, f64>,
+    fraction: f64,
+) -> Result<Vec<VariableConfig>, String> {
+    variables
+        .iter()
+        .map(|var| {
+            let count = values_for(var).len();
+
+            // a swept round only takes whole numbers, and there's nothing to
+            // narrow with a single value
+            if var.name == ROUND_VARIABLE || count < 2 {
+                return Ok(var.clone());
+            }
+
+            let value = *best
+                .get(&var.name)
+                .ok_or_else(|| format!("the stored best has no value for {}", var.name))?;
+
+            let (low, high) = (var.start.min(var.end), var.start.max(var.end));
+            let radius = (high - low) * fraction;
+
+            Ok(VariableConfig {
+                start: (value - radius).clamp(low, high),
+                end: (value + radius).clamp(low, high),
+                step: None,
+                count: Some(count | 1),
+                force_endpoints: false,
+                ..var.clone()
+            })
+        })
+        .collect()
+}
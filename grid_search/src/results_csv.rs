@@ -0,0 +1,252 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use csv::{QuoteStyle, ReaderBuilder, WriterBuilder};
+use serde_json::Value;
+
+use crate::{
+    config::{Config, CsvQuote},
+    create_file,
+    evaluation::{Evaluation, RunOutcome},
+};
+
+// a row per combination, tagged with the sweep it came from so several
+// sweeps can share one file
+pub fn write_results_csv(
+    evaluations: &[Evaluation],
+    cfg: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(path) = &cfg.results_csv else {
+        return Ok(());
+    };
+
+    let columns = columns(cfg);
+    let existing = check_results_csv(cfg)?;
+
+    let tag = cfg.run_tag.clone().unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())
+            .to_string()
+    });
+
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(cfg.append_results)
+        .truncate(!cfg.append_results)
+        .open(path)?;
+
+    let mut writer = WriterBuilder::new()
+        .delimiter(cfg.csv_delimiter as u8)
+        .quote_style(match cfg.csv_quote {
+            CsvQuote::Necessary => QuoteStyle::Necessary,
+            CsvQuote::Always => QuoteStyle::Always,
+            CsvQuote::NonNumeric => QuoteStyle::NonNumeric,
+            CsvQuote::Never => QuoteStyle::Never,
+        })
+        .from_writer(file);
+
+    if existing.is_none() {
+        writer.write_record(&columns)?;
+    }
+
+    let mut evaluations: Vec<&Evaluation> = evaluations.iter().collect();
+    evaluations.sort_by_key(|e| e.index);
+
+    for evaluation in evaluations {
+        let row = columns
+            .iter()
+            .map(|column| cell(column, evaluation, &tag))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        writer.write_record(&row)?;
+    }
+
+    writer.flush()?;
+
+    // the csv alone can't say which sweep it's from, so merging it as a
+    // slice checks against this
+    create_file(
+        &serde_json::to_string_pretty(cfg)?,
+        &results_config_path(path),
+    )?;
+
+    Ok(())
+}
+
+// next to results.csv as results.config.json
+pub fn results_config_path(path: &str) -> PathBuf {
+    Path::new(path).with_extension("config.json")
+}
+
+// the evaluations in a results csv `write_results_csv` wrote, for merging the
+// files of a sweep run in slices. columns the csv doesn't have are left empty
+pub fn read_results_csv(
+    path: &str,
+    cfg: &Config,
+) -> Result<Vec<Evaluation>, Box<dyn std::error::Error>> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(cfg.csv_delimiter as u8)
+        .from_path(path)?;
+    let headers: Vec<String> = reader.headers()?.iter().map(String::from).collect();
+
+    if headers != columns(cfg) {
+        eprintln!(
+            "warning: {} has columns {}, it may have been written by a different config",
+            path,
+            headers.join(",")
+        );
+    }
+
+    if !headers.iter().any(|header| header == "index") {
+        return Err(format!("{} has no index column to merge on", path).into());
+    }
+
+    let mut evaluations = Vec::new();
+
+    for record in reader.records() {
+        let record = record?;
+        let field = |name: &str| {
+            headers
+                .iter()
+                .position(|header| header == name)
+                .and_then(|i| record.get(i))
+                .filter(|value| !value.is_empty())
+        };
+        let number = |name: &str| -> Result<Option<f64>, String> {
+            field(name)
+                .map(|value| {
+                    value
+                        .parse::<f64>()
+                        .map_err(|_| format!("{} has {} {:?}, not a number", path, name, value))
+                })
+                .transpose()
+        };
+
+        let index = field("index")
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| format!("{} has a row without a valid index", path))?;
+
+        let mut parameters = BTreeMap::new();
+        for name in cfg.variables.iter().map(|var| &var.name) {
+            if let Some(value) = number(name)? {
+                parameters.insert(name.clone(), value);
+            }
+        }
+
+        let profit = number("profit")?;
+        let outcome = match field("outcome") {
+            Some(outcome) => serde_json::from_value(Value::from(outcome))
+                .map_err(|_| format!("{} has an unknown outcome {:?}", path, outcome))?,
+            None if profit.is_some() => RunOutcome::Success,
+            None => RunOutcome::NoProfit,
+        };
+
+        evaluations.push(Evaluation {
+            index,
+            parameters,
+            profit,
+            outcome,
+            raw_profit: number("raw_profit")?,
+            baseline_delta: number("baseline_delta")?,
+            profit_std: number("profit_std")?,
+            duration_secs: number("duration_secs")?.unwrap_or_default(),
+            error: field("error").map(String::from),
+        });
+    }
+
+    Ok(evaluations)
+}
+
+// appending rows under another sweep's columns would misalign them, so this
+// is checked before the sweep starts as well as when it's written. returns
+// the header being appended under, if there's one
+pub fn check_results_csv(cfg: &Config) -> Result<Option<Vec<String>>, Box<dyn std::error::Error>> {
+    let Some(path) = cfg.results_csv.as_ref().filter(|_| cfg.append_results) else {
+        return Ok(None);
+    };
+
+    let contents = fs::read_to_string(path).unwrap_or_default();
+    if contents.is_empty() {
+        return Ok(None);
+    }
+
+    let found: Vec<String> = ReaderBuilder::new()
+        .delimiter(cfg.csv_delimiter as u8)
+        .from_reader(contents.as_bytes())
+        .headers()?
+        .iter()
+        .map(String::from)
+        .collect();
+    let columns = columns(cfg);
+
+    if found != columns {
+        return Err(format!(
+            "{} has columns {}, but this sweep writes {}",
+            path,
+            found.join(","),
+            columns.join(",")
+        )
+        .into());
+    }
+
+    Ok(Some(found))
+}
+
+// what a column can hold besides the variables and derived constants
+pub const RESULTS_CSV_FIELDS: [&str; 9] = [
+    "tag",
+    "index",
+    "outcome",
+    "profit",
+    "duration_secs",
+    "raw_profit",
+    "baseline_delta",
+    "profit_std",
+    "error",
+];
+
+fn cell(
+    column: &str,
+    evaluation: &Evaluation,
+    tag: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let optional = |value: Option<f64>| value.map_or_else(String::new, |v| v.to_string());
+
+    Ok(match column {
+        "tag" => tag.to_string(),
+        "index" => evaluation.index.to_string(),
+        "outcome" => serde_json::to_value(evaluation.outcome)?
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        "profit" => optional(evaluation.profit),
+        "duration_secs" => evaluation.duration_secs.to_string(),
+        "raw_profit" => optional(evaluation.raw_profit),
+        "baseline_delta" => optional(evaluation.baseline_delta),
+        "profit_std" => optional(evaluation.profit_std),
+        "error" => evaluation.error.clone().unwrap_or_default(),
+        name => optional(evaluation.parameters.get(name).copied()),
+    })
+}
+
+fn columns(cfg: &Config) -> Vec<String> {
+    if !cfg.csv_columns.is_empty() {
+        return cfg.csv_columns.clone();
+    }
+
+    let mut columns: Vec<String> = RESULTS_CSV_FIELDS[..5]
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+
+    columns.extend(cfg.variables.iter().map(|var| var.name.clone()));
+    columns.extend(cfg.derived.iter().map(|derived| derived.name.clone()));
+
+    columns
+}
@@ -1,80 +1,1868 @@
-use crate::{get_log_path, get_profit};
-use std::process::Command;
-use std::{path::PathBuf, process::Stdio};
+use crate::{
+    analysis::{
+        COVERAGE_BINS, OrderEnd, break_tie, coverage, dead_parameters, min_distance, order_end,
+        parameter_summaries, plateau, sharp_peaks, timeout_clusters,
+    },
+    backtester::find_backtester,
+    budget::OutputBudget,
+    combinations_from, count_combinations,
+    cursor::{CHECKPOINT_INTERVAL, load_cursor, next_unfinished, save_cursor, sweep_hash},
+    disk::check_space,
+    evaluation::{Evaluation, RunOutcome, parse_constants},
+    get_log_path, get_profit,
+    log_writer::LogSink,
+    manifest::{load_manifests, load_manifests_before, write_manifests},
+    normalize_profit, objective_value,
+    plot::vega_lite_spec,
+    pool::run_in_pool,
+    progress::ProgressLine,
+    results_csv::{check_results_csv, read_results_csv, results_config_path, write_results_csv},
+    script_header,
+    state::{State, best_paths, save_state},
+    tail_lines,
+    timing::{command_line, record_timing},
+};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, Command};
+use std::sync::{
+    Mutex,
+    atomic::{AtomicUsize, Ordering},
+};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+};
 
 use crate::{
-    args::Options, config::Config, create_file, get_script_path, read_file, replace_constants,
+    Template,
+    config::{
+        Config, ParseFailure, PreCommand, ROUND_VARIABLE, ScriptDelivery, Strategy, WorkerMode,
+        diff_configs,
+    },
+    create_file, format_constants, get_bucket_dir, get_script_path, read_file, replace_constants,
+    script_constants, template_constants,
 };
 use rayon::prelude::*;
+use serde_json::json;
+
+const ABORT_LOG_TAIL_LINES: usize = 40;
+const FAILURE_STDERR_TAIL_LINES: usize = 20;
+
+// how often a running backtester is checked for having exited
+const EXIT_POLL: Duration = Duration::from_millis(10);
+
+// set for every spawned backtester so it, or the strategy, can keep its own
+// files apart per combination. pool workers get both in each request instead
+pub const INDEX_ENV: &str = "GRID_SEARCH_INDEX";
+pub const PARAMETERS_ENV: &str = "GRID_SEARCH_PARAMS";
+
+// passed to the runner for the baseline and validation runs, which aren't combinations
+const BASELINE_INDEX: usize = usize::MAX;
+const VALIDATION_INDEX: usize = usize::MAX - 1;
+
+// blocklist values are matched within this of the parsed constants
+const BLOCKLIST_TOLERANCE: f64 = 1e-9;
+
+// starts the log of a run scored on what it flushed at the soft timeout
+pub const SOFT_STOP_PREFIX: &str = "Stopped early, ";
+
+// name of the copy of the config written into logs_dir
+const CONFIG_COPY: &str = "config.json";
+
+const PLOT_SPEC: &str = "plot.vl.json";
+
+// number of finished combinations used to project the sweep's disk usage
+const DISK_CALIBRATION_RUNS: usize = 5;
+
+pub type WorkerError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, Clone)]
+pub struct Best {
+    pub profit: f64,
+    pub constants: String,
+    pub evaluation: Evaluation,
+
+    // the best's profit on validation_round, when one is set
+    pub validation_profit: Option<f64>,
+}
+
+// runs a single combination's script, the backtester outside of tests
+pub trait Runner: Sync {
+    fn run(
+        &self,
+        i: usize,
+        parameters: &BTreeMap<String, f64>,
+        script_path: &Path,
+        stdin: Option<String>,
+        cfg: &Config,
+        in_flight: &InFlight,
+    ) -> Result<RunOutput, WorkerError>;
+
+    // what the run time is cached against in timing_cache, runners without
+    // one aren't timed
+    fn command(&self, _cfg: &Config) -> Option<String> {
+        None
+    }
+
+    // the constants block written into the script in place of the usual
+    // `name = value` lines, for strategies wanting e.g. a dict literal
+    fn constants_block(
+        &self,
+        _parameters: &BTreeMap<String, f64>,
+        _cfg: &Config,
+    ) -> Result<Option<String>, WorkerError> {
+        Ok(None)
+    }
+}
+
+// another runner, with its constants block built by `hook` from the parameters
+pub struct ConstantsHook<R, F> {
+    pub runner: R,
+    pub hook: F,
+}
+
+impl<R, F> Runner for ConstantsHook<R, F>
+where
+    R: Runner,
+    F: Fn(&BTreeMap<String, f64>) -> String + Sync,
+{
+    fn run(
+        &self,
+        i: usize,
+        parameters: &BTreeMap<String, f64>,
+        script_path: &Path,
+        stdin: Option<String>,
+        cfg: &Config,
+        in_flight: &InFlight,
+    ) -> Result<RunOutput, WorkerError> {
+        self.runner
+            .run(i, parameters, script_path, stdin, cfg, in_flight)
+    }
 
-pub fn run_all(
-    constant_strings: &Vec<String>,
+    fn command(&self, cfg: &Config) -> Option<String> {
+        self.runner.command(cfg)
+    }
+
+    fn constants_block(
+        &self,
+        parameters: &BTreeMap<String, f64>,
+        _cfg: &Config,
+    ) -> Result<Option<String>, WorkerError> {
+        Ok(Some((self.hook)(parameters)))
+    }
+}
+
+// the combinations of one sweep with a backtester running, and when each
+// started, so the skip file stops the longest running of that sweep's alone
+#[derive(Default)]
+pub struct InFlight(Mutex<BTreeMap<usize, Instant>>);
+
+pub struct RunOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit: Exit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exit {
+    Finished,
+    // exited on its own after the soft timeout asked it to stop
+    Terminated,
+    TimedOut,
+    Skipped,
+}
+
+pub struct Backtester {
+    pub path: PathBuf,
+}
+
+impl Runner for Backtester {
+    fn run(
+        &self,
+        i: usize,
+        parameters: &BTreeMap<String, f64>,
+        script_path: &Path,
+        stdin: Option<String>,
+        cfg: &Config,
+        in_flight: &InFlight,
+    ) -> Result<RunOutput, WorkerError> {
+        match cfg.worker_mode {
+            WorkerMode::Spawn => run_script(
+                &self.path,
+                &backtester_args(cfg, script_path),
+                stdin,
+                i,
+                parameters,
+                cfg,
+                in_flight,
+            ),
+            WorkerMode::Pool => {
+                let (output, stderr) = run_in_pool(&self.path, cfg, i, parameters, script_path)?;
+
+                Ok(RunOutput {
+                    stdout: match cfg.capture_tail_lines {
+                        Some(n) => tail_lines(&output, n),
+                        None => output,
+                    },
+                    stderr,
+                    exit: Exit::Finished,
+                })
+            }
+        }
+    }
+
+    fn command(&self, cfg: &Config) -> Option<String> {
+        Some(command_line(&self.path, cfg))
+    }
+}
+
+pub fn run_all(cfg: &Config, threads: usize) -> Result<Option<Best>, Box<dyn std::error::Error>> {
+    let backtester = find_backtester(&cfg.backtester_search_paths)?;
+    println!("Using backtester at {}", backtester.display());
+
+    run_all_with(cfg, threads, &Backtester { path: backtester })
+}
+
+pub fn run_all_with(
     cfg: &Config,
-    opts: &Options,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let pool = match rayon::ThreadPoolBuilder::new()
-        .num_threads(opts.threads as usize)
-        .build()
+    threads: usize,
+    runner: &dyn Runner,
+) -> Result<Option<Best>, Box<dyn std::error::Error>> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()?;
+
+    // variables can be left out of the config for --ranges-csv, so this is
+    // only known once the sweep is about to run
+    if cfg.variables.is_empty() {
+        if !cfg.allow_empty {
+            return Err(
+                "no variables to sweep, set allow_empty to run the template once as it is".into(),
+            );
+        }
+
+        println!("No variables to sweep, running the template once as it is");
+    }
+
+    // a resumed sweep starts generating at its cursor, so neither the
+    // combinations before it nor their logs are looked at to find where it
+    // got to. the few past the cursor that had finished early run again
+    let start = match cfg.resume {
+        true => load_cursor(cfg)?.next,
+        false => 0,
+    };
+
+    let mut selected = combinations_from(cfg, start)
+        .enumerate()
+        .map(|(offset, constants)| (start + offset, constants))
+        .filter(|(i, _)| cfg.slice.is_none_or(|slice| slice.contains(*i)))
+        .collect::<Vec<_>>();
+
+    // what earlier runs of this sweep finished, for the manifests rewritten
+    // at each checkpoint and the stats once the sweep's done
+    let mut resumed = Vec::new();
+    if cfg.resume {
+        resumed = load_manifests_before(&cfg.logs_dir, start)?;
+
+        // a logs_dir holding little more than the cursor, e.g. moved to
+        // another machine, may not have the buckets still to run
+        for (i, _) in &selected {
+            let bucket = get_bucket_dir(*i, cfg);
+
+            if !bucket.exists() {
+                fs::create_dir_all(bucket.join("logs"))?;
+                fs::create_dir_all(bucket.join("scripts"))?;
+            }
+        }
+
+        println!(
+            "Resuming from combination {}, {} already finished",
+            start,
+            resumed.len()
+        );
+    }
+
+    let before_blocklist = selected.len();
+    if !cfg.blocklist.is_empty() {
+        selected.retain(|(_, constants)| !is_blocked(&parse_constants(constants), cfg));
+    }
+    let blocked = before_blocklist - selected.len();
+
+    if let Some(max_runs) = cfg.max_runs
+        && selected.len() > max_runs
     {
-        Err(e) => return Err(e.into()),
-        Ok(pool) => pool,
+        println!(
+            "Running only the first {} of {} combinations (max_runs)",
+            max_runs,
+            selected.len()
+        );
+        selected.truncate(max_runs);
+    }
+
+    check_results_csv(cfg)?;
+
+    let template_contents = read_file(&cfg.script)?;
+    let template = Template::split(&template_contents, &cfg.start_marker, &cfg.end_marker)
+        .map_err(|why| format!("{}: {}", cfg.script, why))?;
+
+    // every combination writes at least a copy of the script
+    let template_len = template_contents.len() as u64;
+    check_space(
+        Path::new(&cfg.logs_dir),
+        template_len * selected.len() as u64,
+        cfg.abort_on_low_disk,
+    )?;
+
+    if cfg.script_delivery == ScriptDelivery::Ram {
+        fs::create_dir_all(ram_script_dir())?;
+    }
+
+    if let Some(pre) = &cfg.pre_command {
+        run_pre_command(pre)?;
+    }
+
+    // kept with the results so merged slices can be checked against each other
+    create_file(
+        &serde_json::to_string_pretty(cfg)?,
+        &Path::new(&cfg.logs_dir).join(CONFIG_COPY),
+    )?;
+
+    if cfg.worker_mode == WorkerMode::Spawn {
+        // a leftover from an earlier sweep would skip the first combination
+        let _ = fs::remove_file(skip_file_path(cfg));
+        println!(
+            "Create {} to skip the longest running combination",
+            skip_file_path(cfg).display()
+        );
+    }
+
+    let events = match &cfg.events_file {
+        Some(events_file) => Some(
+            fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(events_file)
+                .map_err(|why| format!("couldn't open {}: {}", events_file, why))?,
+        ),
+        None => None,
+    };
+
+    let last_failure: Mutex<Option<usize>> = Mutex::new(None);
+    let errored = AtomicUsize::new(0);
+    let live_best: Mutex<Option<Best>> = Mutex::new(None);
+    let evaluations: Mutex<Vec<Evaluation>> = Mutex::new(Vec::new());
+    // the combination that reached target_objective, and how many had finished by then
+    let target_hit: Mutex<Option<(usize, usize)>> = Mutex::new(None);
+    let budget = cfg
+        .max_output_bytes
+        .map(|limit| Mutex::new(OutputBudget::new(limit, cfg.on_output_budget)));
+
+    let baseline = match cfg.baseline {
+        true => Some(run_baseline(cfg, runner)?),
+        false => None,
     };
 
-    let mut curr_max_profit = f64::MIN;
-    let mut max_constants = String::new();
+    let in_flight = InFlight::default();
+    let (logs, log_writer) = LogSink::new(cfg);
+    let progress = ProgressLine::new(cfg.progress, selected.len());
+    let selected_indices = selected.iter().map(|(i, _)| *i).collect::<Vec<_>>();
+    let last_checkpoint = Mutex::new(Instant::now());
 
-    pool.install(|| {
-        constant_strings
+    let res = pool.install(|| {
+        selected
             .par_iter()
-            .enumerate()
-            .for_each(|(i, constants)| {
-                // compare to max
-                // ? update max
+            .try_for_each(|(i, constants)| -> Result<(), WorkerError> {
+                let i = *i;
 
-                let orig_script_contents = read_file(&cfg.script).unwrap();
-                let new_script_contents = replace_constants(&orig_script_contents, constants);
-                let new_script_path = get_script_path(i, &cfg.logs_dir);
+                // what's already running finishes, nothing new starts
+                if target_hit.lock().unwrap().is_some() {
+                    return Ok(());
+                }
 
-                create_file(&new_script_contents, &new_script_path);
+                let mut evaluation = match run_combination(
+                    i, constants, &template, cfg, runner, &logs, &in_flight,
+                ) {
+                    Ok(evaluation) => evaluation,
+                    Err(why) if cfg.continue_on_error => {
+                        eprintln!("combination {} errored: {}", i, why);
+                        errored.fetch_add(1, Ordering::Relaxed);
 
-                let mut stdout = String::new();
-                let mut stderr = String::new();
+                        Evaluation {
+                            index: i,
+                            parameters: parse_constants(constants),
+                            profit: None,
+                            outcome: RunOutcome::Error,
+                            raw_profit: None,
+                            baseline_delta: None,
+                            profit_std: None,
+                            duration_secs: 0.,
+                            error: Some(why.to_string()),
+                        }
+                    }
+                    Err(why) => return Err(why),
+                };
 
-                run_script(&new_script_path, cfg.round, &mut stdout, &mut stderr);
+                // under on_parse_failure zero a missing profit competes as 0
+                let counted = matches!(
+                    evaluation.outcome,
+                    RunOutcome::Success | RunOutcome::SoftTimeout
+                ) || (evaluation.outcome == RunOutcome::NoProfit
+                    && cfg.on_parse_failure == ParseFailure::Zero);
 
-                let log_contents: String;
-                let log_path = get_log_path(i, &cfg.logs_dir);
+                if counted {
+                    let profit = evaluation.profit.unwrap();
+                    evaluation.baseline_delta = baseline.map(|baseline| profit - baseline);
 
-                let profit = get_profit(&stdout);
+                    let mut live_best = live_best.lock().unwrap();
 
-                if let Some(profit) = profit {
-                    log_contents = format!(
-                        "Stdout:\n{}\n\n\nStderr:\n{}\n\n\nProfit: {}",
-                        stdout, stderr, profit
-                    );
-                } else {
-                    log_contents = format!(
-                        "Stdout:\n{}\n\n\nStderr:\n{}\n\n\nNo profit found.",
-                        stdout, stderr
-                    );
+                    if live_best.as_ref().is_none_or(|best| {
+                        profit > best.profit + cfg.min_improvement
+                            || (profit == best.profit
+                                && wins_tie(&evaluation, &best.evaluation, cfg))
+                    }) {
+                        match evaluation.baseline_delta {
+                            Some(delta) => println!(
+                                "[NEW MAX] {} ({:+} vs baseline) with: {}",
+                                profit, delta, constants
+                            ),
+                            None => println!("[NEW MAX] {} with: {}", profit, constants),
+                        }
+
+                        let best = Best {
+                            profit,
+                            constants: constants.clone(),
+                            evaluation: evaluation.clone(),
+                            validation_profit: None,
+                        };
+
+                        if let Some(state_file) = &cfg.state_file {
+                            save_state(state_file, &State::new(&best, cfg))
+                                .map_err(|e| e.to_string())?;
+                        }
+
+                        *live_best = Some(best);
+                    }
                 }
 
-                create_file(&log_contents, &log_path);
-            });
+                if evaluation.outcome == RunOutcome::NoProfit {
+                    *last_failure.lock().unwrap() = Some(i);
+
+                    if cfg.on_parse_failure == ParseFailure::Abort {
+                        return Err(format!("combination {} produced no profit", i).into());
+                    }
+                }
+
+                let mut evaluations = evaluations.lock().unwrap();
+
+                // written under the lock so events arrive in completion order
+                if let Some(mut events) = events.as_ref() {
+                    let best = live_best.lock().unwrap().as_ref().map(|best| best.profit);
+                    let event = json!({
+                        "index": evaluation.index,
+                        "parameters": evaluation.parameters,
+                        "profit": evaluation.profit,
+                        "outcome": evaluation.outcome,
+                        "best": best,
+                        "completed": evaluations.len() + 1,
+                        "total": selected.len(),
+                    });
+
+                    writeln!(events, "{}", event)?;
+                }
+
+                if let Some(budget) = &budget {
+                    budget.lock().unwrap().record(&evaluation, cfg)?;
+                }
+
+                let hit = evaluation.outcome == RunOutcome::Success
+                    && cfg
+                        .target_objective
+                        .is_some_and(|target| evaluation.profit.unwrap() >= target);
+
+                evaluations.push(evaluation);
+                progress.update(evaluations.len());
+
+                let mut last_checkpoint = last_checkpoint.lock().unwrap();
+                if last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+                    checkpoint(&resumed, &evaluations, &selected_indices, cfg)
+                        .map_err(|why| why.to_string())?;
+                    *last_checkpoint = Instant::now();
+                }
+
+                if hit {
+                    target_hit
+                        .lock()
+                        .unwrap()
+                        .get_or_insert((i, evaluations.len()));
+                }
+
+                if evaluations.len() == DISK_CALIBRATION_RUNS {
+                    let indices = evaluations.iter().map(|e| e.index).collect::<Vec<_>>();
+                    let per_run =
+                        script_bytes(&indices, cfg) / indices.len() as u64 + logs.mean_log_bytes();
+                    let remaining = (selected.len() - indices.len()) as u64;
+
+                    check_space(
+                        Path::new(&cfg.logs_dir),
+                        per_run * remaining,
+                        cfg.abort_on_low_disk,
+                    )?;
+                }
+
+                Ok(())
+            })
     });
 
+    progress.finish();
+
+    if cfg.script_delivery == ScriptDelivery::Ram {
+        let _ = fs::remove_dir_all(ram_script_dir());
+    }
+
+    // closing the sink lets the writer finish what's buffered and stop
+    drop(logs);
+    if let Some(log_writer) = log_writer {
+        log_writer.join().map_err(|_| "log writer panicked")??;
+    }
+
+    let evaluations = evaluations.into_inner()?;
+    checkpoint(&resumed, &evaluations, &selected_indices, cfg)?;
+
+    let resumed_len = resumed.len();
+    let evaluations = [resumed, evaluations].concat();
+    write_results_csv(&evaluations, cfg)?;
+
+    let target_hit = target_hit.into_inner()?;
+    if let Some((index, completed)) = target_hit {
+        println!(
+            "Combination {} reached target_objective {} after {} of {} combinations, stopped early",
+            index,
+            cfg.target_objective.unwrap(),
+            completed,
+            selected.len()
+        );
+    }
+
+    if cfg.plot_spec {
+        write_plot_spec(&evaluations, cfg)?;
+    }
+
+    if let (Some(timing_cache), Some(command)) = (&cfg.timing_cache, runner.command(cfg))
+        && let Err(why) = record_timing(
+            timing_cache,
+            &command,
+            &evaluations[resumed_len..],
+            cfg.repeats,
+        )
+    {
+        eprintln!("warning: couldn't update {}: {}", timing_cache, why);
+    }
+
+    let errored = errored.into_inner();
+    if errored > 0 {
+        eprintln!(
+            "{} of {} combinations errored, see the index.json manifests for details",
+            errored,
+            selected.len()
+        );
+    }
+
+    if blocked > 0 {
+        eprintln!("{} combinations were blocked by the blocklist", blocked);
+    }
+
+    let skipped = evaluations
+        .iter()
+        .filter(|e| e.outcome == RunOutcome::Skipped)
+        .count();
+    if skipped > 0 {
+        eprintln!("{} combinations were skipped", skipped);
+    }
+
+    let non_finite = evaluations
+        .iter()
+        .filter(|e| e.outcome == RunOutcome::NonFinite)
+        .count();
+    if non_finite > 0 {
+        eprintln!(
+            "{} combinations produced an inf or nan profit and were left out",
+            non_finite
+        );
+    }
+
+    if let Some(budget) = budget {
+        let evicted = budget.into_inner()?.evicted;
+
+        if evicted > 0 {
+            eprintln!(
+                "deleted the scripts and logs of {} low profit combinations to stay under max_output_bytes",
+                evicted
+            );
+        }
+    }
+
+    print_timeouts(&evaluations, cfg);
+
+    if let Err(e) = res {
+        if let Some(i) = *last_failure.lock().unwrap() {
+            print_log_tail(i, cfg);
+        }
+
+        return Err(e);
+    }
+
+    // runs still in flight when the target was hit can finish above it, but
+    // the sweep stopped for the one that hit it
+    let mut best = pick_best(&evaluations, cfg, target_hit.map(|(index, _)| index), |i| {
+        combinations_from(cfg, i).next().unwrap_or_default()
+    })?;
+
+    if let (Some(best), Some(round)) = (&mut best, cfg.validation_round) {
+        let profit = run_validation(best, round, cfg, runner)?;
+
+        println!(
+            "Validation profit {} on round {} ({:+} vs the {} picked on round {})",
+            profit,
+            round,
+            profit - best.profit,
+            best.profit,
+            cfg.round
+        );
+
+        best.validation_profit = Some(profit);
+    }
+
+    // after everything's written, so a sweep failing this still keeps its results
+    check_successes(&evaluations, cfg)?;
+
+    Ok(best)
+}
+
+// for failing a sweep that finished but mostly without a profit
+fn check_successes(evaluations: &[Evaluation], cfg: &Config) -> Result<(), String> {
+    let successes = evaluations
+        .iter()
+        .filter(|e| e.outcome == RunOutcome::Success && e.profit.is_some())
+        .count();
+    let total = evaluations.len();
+
+    if let Some(min) = cfg.min_successes
+        && successes < min
+    {
+        return Err(format!(
+            "only {} of {} combinations produced a profit, min_successes is {}",
+            successes, total, min
+        ));
+    }
+
+    if let Some(rate) = cfg.min_success_rate
+        && (successes as f64) < rate * total as f64
+    {
+        return Err(format!(
+            "only {} of {} combinations produced a profit, under the min_success_rate of {}",
+            successes, total, rate
+        ));
+    }
+
+    Ok(())
+}
+
+// how fast the backtester got through the bench runs at one thread count
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+    pub threads: usize,
+    pub runs: usize,
+    pub secs: f64,
+    pub mean_run_secs: f64,
+}
+
+impl BenchResult {
+    pub fn runs_per_sec(&self) -> f64 {
+        self.runs as f64 / self.secs
+    }
+}
+
+// runs one combination `runs` times over `threads` workers, for picking
+// --threads before a real sweep. its script is written once, as bench.py in
+// logs_dir, and nothing else is kept
+pub fn bench_with(
+    constants: &str,
+    cfg: &Config,
+    threads: usize,
+    runs: usize,
+    runner: &dyn Runner,
+) -> Result<BenchResult, Box<dyn std::error::Error>> {
+    let template = Template::split(&read_file(&cfg.script)?, &cfg.start_marker, &cfg.end_marker)?;
+    let parameters = parse_constants(constants);
+    let block =
+        constants_block(constants, &parameters, cfg, runner).map_err(|why| why.to_string())?;
+    let contents = template.fill(&block);
+
+    let script_path = Path::new(&cfg.logs_dir).join("bench.py");
+    let stdin = match cfg.script_delivery {
+        ScriptDelivery::Stdin => Some(contents),
+        _ => {
+            create_file(&contents, &script_path)?;
+            None
+        }
+    };
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()?;
+    let in_flight = InFlight::default();
+    let started = Instant::now();
+
+    let run_secs = pool
+        .install(|| {
+            (0..runs)
+                .into_par_iter()
+                .map(|i| -> Result<f64, WorkerError> {
+                    let started = Instant::now();
+                    let output =
+                        runner.run(i, &parameters, &script_path, stdin.clone(), cfg, &in_flight)?;
+
+                    if output.exit != Exit::Finished {
+                        return Err(format!("bench run {} didn't finish", i).into());
+                    }
+
+                    Ok(started.elapsed().as_secs_f64())
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|why| why.to_string())?;
+
+    Ok(BenchResult {
+        threads,
+        runs,
+        secs: started.elapsed().as_secs_f64(),
+        mean_run_secs: mean(&run_secs),
+    })
+}
+
+// the manifests first, so the cursor never points past what they hold
+fn checkpoint(
+    resumed: &[Evaluation],
+    evaluations: &[Evaluation],
+    selected: &[usize],
+    cfg: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let all = [resumed, evaluations].concat();
+    write_manifests(&all, cfg)?;
+
+    let finished = all.iter().map(|e| e.index).collect();
+    save_cursor(cfg, next_unfinished(selected, &finished))
+}
+
+// the overall best of a sweep that was split into slices, from each slice's
+// results csv or the manifests in its logs dir. the merged evaluations go to
+// the config's results_csv and the best to its state_file
+pub fn merge_slices(
+    inputs: &[String],
+    cfg: &Config,
+) -> Result<Option<Best>, Box<dyn std::error::Error>> {
+    let mut merged: BTreeMap<usize, Evaluation> = BTreeMap::new();
+
+    for input in inputs {
+        let evaluations = if Path::new(input).is_dir() {
+            match slice_config(&Path::new(input).join(CONFIG_COPY)) {
+                Some(slice_cfg) => check_same_sweep(input, slice_cfg, cfg),
+                None => eprintln!(
+                    "warning: {} has no readable {}, can't check it ran the same sweep",
+                    input, CONFIG_COPY
+                ),
+            }
+
+            load_manifests(input)?
+        } else {
+            let config_path = results_config_path(input);
+            let slice_cfg = slice_config(&config_path).ok_or_else(|| {
+                format!(
+                    "{} has no readable {} next to it, can't check it ran the same sweep",
+                    input,
+                    config_path.display()
+                )
+            })?;
+            check_same_sweep(input, slice_cfg, cfg);
+
+            read_results_csv(input, cfg)?
+        };
+
+        for evaluation in evaluations {
+            if let Some(previous) = merged.insert(evaluation.index, evaluation) {
+                eprintln!(
+                    "warning: combination {} was run more than once",
+                    previous.index
+                );
+            }
+        }
+    }
+
+    let evaluations = merged.into_values().collect::<Vec<_>>();
+    let total = count_combinations(cfg);
+
+    println!(
+        "Merged {} of {} combinations from {} slices",
+        evaluations.len(),
+        total,
+        inputs.len()
+    );
+
+    if (evaluations.len() as u128) < total {
+        eprintln!(
+            "warning: {} combinations haven't been run by any slice",
+            total - evaluations.len() as u128
+        );
+    }
+
+    write_results_csv(&evaluations, cfg)?;
+
+    pick_best(&evaluations, cfg, None, |i| {
+        combinations_from(cfg, i).next().unwrap_or_default()
+    })
+}
+
+// an exact tie with the live best goes the way pick_best would break it, so
+// the state file never depends on which of the two finished first
+fn wins_tie(evaluation: &Evaluation, best: &Evaluation, cfg: &Config) -> bool {
+    let mut pair = [best, evaluation];
+    pair.sort_by_key(|e| e.index);
+
+    break_tie(&pair, &cfg.variables, cfg.tie_break)
+        .is_some_and(|winner| winner.index == evaluation.index)
+}
+
+fn pick_best(
+    evaluations: &[Evaluation],
+    cfg: &Config,
+    target_hit: Option<usize>,
+    constants_for: impl Fn(usize) -> String,
+) -> Result<Option<Best>, Box<dyn std::error::Error>> {
+    for name in dead_parameters(evaluations, &cfg.variables) {
+        eprintln!(
+            "warning: changing {} never changed the profit, check that it's used by the strategy",
+            display_name(&name, cfg)
+        );
+    }
+
+    // picking the winner after the fact rather than as results arrive keeps
+    // it independent of the order the workers happened to finish in
+    let plateau = plateau(evaluations, cfg.plateau_epsilon);
+
+    if cfg.plateau_epsilon > 0. {
+        println!(
+            "{} combinations within {} of the best profit: {:?}",
+            plateau.len(),
+            cfg.plateau_epsilon,
+            plateau.iter().map(|e| e.index).collect::<Vec<_>>()
+        );
+    }
+
+    let winner = match target_hit {
+        Some(index) => evaluations.iter().find(|e| e.index == index),
+        None => break_tie(&plateau, &cfg.variables, cfg.tie_break),
+    };
+
+    let best = winner.map(|winner| Best {
+        profit: winner.profit.unwrap(),
+        constants: constants_for(winner.index),
+        evaluation: winner.clone(),
+        validation_profit: None,
+    });
+
+    if let Some(best) = &best {
+        print_summary(best, evaluations, cfg);
+
+        // the tie-broken winner can differ from the last live best
+        if let Some(state_file) = &cfg.state_file {
+            save_state(state_file, &State::new(best, cfg))?;
+        }
+    }
+
+    Ok(best)
+}
+
+// runs the template as it is, or with baseline_parameters, before the grid so
+// every combination can be compared against it
+fn run_baseline(cfg: &Config, runner: &dyn Runner) -> Result<f64, Box<dyn std::error::Error>> {
+    let script = read_file(&cfg.script)?;
+
+    let (contents, parameters) = match &cfg.baseline_parameters {
+        Some(parameters) => {
+            let constants = format_constants(parameters, cfg)
+                .map_err(|why| format!("baseline_parameters has {}", why))?;
+            let contents = replace_constants(
+                &script,
+                &script_constants(&constants),
+                &cfg.start_marker,
+                &cfg.end_marker,
+            )?;
+
+            (contents, parameters.clone())
+        }
+        None => {
+            // constants elsewhere in the template aren't ones the sweep sets
+            let block = template_constants(&script, &cfg.start_marker, &cfg.end_marker)?;
+
+            (script, parse_constants(&block))
+        }
+    };
+
+    let profit = run_single(
+        "baseline",
+        BASELINE_INDEX,
+        contents,
+        &parameters,
+        cfg,
+        runner,
+    )?;
+
+    println!("Baseline profit {}", profit);
+
+    Ok(profit)
+}
+
+// runs the best's constants on another round, written to logs_dir as validation.py
+fn run_validation(
+    best: &Best,
+    round: u8,
+    cfg: &Config,
+    runner: &dyn Runner,
+) -> Result<f64, Box<dyn std::error::Error>> {
+    let template = read_file(&cfg.script)?;
+    let block = constants_block(&best.constants, &best.evaluation.parameters, cfg, runner)
+        .map_err(|why| why.to_string())?;
+    let contents = replace_constants(&template, &block, &cfg.start_marker, &cfg.end_marker)?;
+
+    let mut validation_cfg = cfg.clone();
+    validation_cfg.round = round;
+
+    run_single(
+        "validation",
+        VALIDATION_INDEX,
+        contents,
+        &best.evaluation.parameters,
+        &validation_cfg,
+        runner,
+    )
+}
+
+// what goes between the markers: the runner's block, or constants_command's,
+// if there's one, the `name = value` lines otherwise
+fn constants_block(
+    constants: &str,
+    parameters: &BTreeMap<String, f64>,
+    cfg: &Config,
+    runner: &dyn Runner,
+) -> Result<String, WorkerError> {
+    if let Some(block) = runner.constants_block(parameters, cfg)? {
+        return Ok(block);
+    }
+
+    match &cfg.constants_command {
+        Some(command) => run_constants_command(command, parameters),
+        None => Ok(script_constants(constants)),
+    }
+}
+
+// its output goes straight through, since a download's progress is worth seeing
+fn run_pre_command(pre: &PreCommand) -> Result<(), Box<dyn std::error::Error>> {
+    let (program, args) = pre.command.split_first().ok_or("pre_command is empty")?;
+
+    let mut command = Command::new(program);
+    command.args(args).envs(&pre.env);
+
+    if let Some(dir) = &pre.dir {
+        command.current_dir(dir);
+    }
+
+    println!("Running pre_command {}", pre.command.join(" "));
+
+    let status = command
+        .status()
+        .map_err(|why| format!("couldn't run pre_command {}: {}", program, why))?;
+
+    if !status.success() {
+        return Err(format!("pre_command {} failed with {}", program, status).into());
+    }
+
     Ok(())
 }
 
-fn run_script(script_path: &PathBuf, round: u8, stdout: &mut String, stderr: &mut String) {
-    let child = Command::new("prosperity3bt")
-        .arg(script_path)
-        .arg(round.to_string())
+// the parameters go to its stdin as a json object, its stdout is the block
+fn run_constants_command(
+    command: &[String],
+    parameters: &BTreeMap<String, f64>,
+) -> Result<String, WorkerError> {
+    let (program, args) = command.split_first().ok_or("constants_command is empty")?;
+
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|why| format!("couldn't run constants_command {}: {}", program, why))?;
+
+    // dropped straight after so the command sees the end of its input
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(serde_json::to_string(parameters)?.as_bytes())?;
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "constants_command {} failed: {}",
+            program,
+            tail_lines(
+                &String::from_utf8_lossy(&output.stderr),
+                FAILURE_STDERR_TAIL_LINES
+            )
+        )
+        .into());
+    }
+
+    Ok(String::from_utf8(output.stdout)?.trim_end().to_string())
+}
+
+// a run outside the grid, its script and log kept in logs_dir as `name`.py and `name`.txt
+fn run_single(
+    name: &str,
+    index: usize,
+    contents: String,
+    parameters: &BTreeMap<String, f64>,
+    cfg: &Config,
+    runner: &dyn Runner,
+) -> Result<f64, Box<dyn std::error::Error>> {
+    let script_path = Path::new(&cfg.logs_dir).join(format!("{}.py", name));
+    let stdin = match cfg.script_delivery {
+        ScriptDelivery::Stdin => Some(contents),
+        _ => {
+            create_file(&contents, &script_path)?;
+            None
+        }
+    };
+
+    // it runs on its own, outside the sweep's runs
+    let in_flight = InFlight::default();
+    let (output, duration_secs, profit) = run_once(
+        index,
+        parameters,
+        &script_path,
+        stdin,
+        cfg,
+        runner,
+        &in_flight,
+    )
+    .map_err(|why| why.to_string())?;
+
+    create_file(
+        &format!("Stdout:\n{}\n\n\nStderr:\n{}", output.stdout, output.stderr),
+        &Path::new(&cfg.logs_dir).join(format!("{}.txt", name)),
+    )?;
+
+    let profit = profit.ok_or(format!("the {} run produced no profit", name))?;
+
+    let profit = match &cfg.objective {
+        Some(objective) => objective_value(objective, profit, duration_secs, parameters)?,
+        None => profit,
+    };
+
+    Ok(profit)
+}
+
+fn run_combination(
+    i: usize,
+    constants: &str,
+    template: &Template,
+    cfg: &Config,
+    runner: &dyn Runner,
+    logs: &LogSink,
+    in_flight: &InFlight,
+) -> Result<Evaluation, WorkerError> {
+    let parameters = parse_constants(constants);
+
+    let round_cfg;
+    let cfg = match parameters.get(ROUND_VARIABLE) {
+        Some(round) => {
+            round_cfg = Config {
+                round: *round as u8,
+                ..cfg.clone()
+            };
+            &round_cfg
+        }
+        None => cfg,
+    };
+
+    let mut new_script_contents =
+        template.fill(&constants_block(constants, &parameters, cfg, runner)?);
+
+    if cfg.script_header {
+        new_script_contents.insert_str(0, &script_header(i, constants));
+    }
+
+    let new_script_path = delivered_script_path(i, cfg);
+
+    let stdin = if cfg.script_delivery == ScriptDelivery::Stdin {
+        Some(new_script_contents)
+    } else {
+        create_file(&new_script_contents, &new_script_path)?;
+        None
+    };
+
+    let started = Instant::now();
+
+    let res = run_repeats(
+        i,
+        &parameters,
+        &new_script_path,
+        stdin,
+        cfg,
+        runner,
+        in_flight,
+    );
+
+    if cfg.script_delivery == ScriptDelivery::Ram {
+        let _ = fs::remove_file(&new_script_path);
+    }
+
+    let runs = res?;
+    let duration_secs = started.elapsed().as_secs_f64();
+
+    let log_contents: String;
+    let log_path = get_log_path(i, cfg);
+
+    let (last, _, last_profit) = runs.last().unwrap();
+
+    // a backtester that flushed a profit when asked to stop is scored on it
+    let soft_stopped = last.exit == Exit::Terminated && last_profit.is_some();
+    let soft_reason = || {
+        format!(
+            "stopped at the {}s soft timeout",
+            cfg.soft_timeout_secs.unwrap()
+        )
+    };
+
+    if last.exit != Exit::Finished && !soft_stopped {
+        let (outcome, reason) = match last.exit {
+            Exit::Terminated => (RunOutcome::Timeout, soft_reason()),
+            Exit::TimedOut => (
+                RunOutcome::Timeout,
+                format!("timed out after {}s", cfg.kill_after_secs().unwrap()),
+            ),
+            _ => (RunOutcome::Skipped, String::from("skipped")),
+        };
+        eprintln!("combination {} {}", i, reason);
+
+        logs.write(
+            format!(
+                "Stopped, {}.\n\n\nStdout:\n{}\n\n\nStderr:\n{}",
+                reason, last.stdout, last.stderr
+            ),
+            log_path,
+        )?;
+
+        return Ok(Evaluation {
+            index: i,
+            parameters,
+            profit: None,
+            outcome,
+            raw_profit: None,
+            baseline_delta: None,
+            profit_std: None,
+            duration_secs,
+            error: Some(reason),
+        });
+    }
+
+    // a repeat without a profit leaves the whole combination without one
+    let raw_profits = runs
+        .iter()
+        .map(|(_, _, profit)| *profit)
+        .collect::<Option<Vec<_>>>();
+
+    // with an objective, its value is what's compared from here on
+    let values = match (&cfg.objective, &raw_profits) {
+        (Some(objective), Some(raw_profits)) => Some(
+            raw_profits
+                .iter()
+                .zip(&runs)
+                .map(|(profit, (_, secs, _))| {
+                    objective_value(objective, *profit, *secs, &parameters)
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        (None, raw_profits) => raw_profits.clone(),
+        (Some(_), None) => None,
+    };
+
+    let raw_profit = raw_profits.as_deref().map(mean);
+    let parsed_profit = values.as_deref().map(mean);
+    let profit_std = values.as_deref().filter(|v| v.len() > 1).map(sample_std);
+
+    // inf or nan, from the backtester or the objective, would break every
+    // comparison, so it's kept out of the profits altogether
+    let non_finite = parsed_profit.filter(|profit| !profit.is_finite());
+
+    let profit = match cfg.on_parse_failure {
+        _ if non_finite.is_some() => None,
+        ParseFailure::Zero => parsed_profit.or(Some(0.)),
+        ParseFailure::Skip | ParseFailure::Abort => parsed_profit,
+    };
+
+    let (stdout, stderr) = match runs.as_slice() {
+        [(output, _, _)] => (output.stdout.clone(), output.stderr.clone()),
+        _ => (
+            join_repeats(&runs, cfg, |output| &output.stdout),
+            join_repeats(&runs, cfg, |output| &output.stderr),
+        ),
+    };
+
+    let objective_line = match (&cfg.objective, parsed_profit) {
+        (Some(_), Some(value)) => format!("\nObjective: {}", value),
+        _ => String::new(),
+    };
+
+    if let Some(profit) = parsed_profit
+        && let Some(threshold) = cfg.log_threshold
+        && profit <= threshold
+    {
+        log_contents = format!("Profit: {} (below log_threshold {})", profit, threshold);
+    } else if let Some(profit) = raw_profit {
+        log_contents = format!(
+            "Stdout:\n{}\n\n\nStderr:\n{}\n\n\nProfit: {}{}",
+            stdout, stderr, profit, objective_line
+        );
+    } else {
+        // the reason is almost always a traceback at the end of stderr, so it
+        // goes first rather than after the whole of stdout
+        log_contents = format!(
+            "No profit found. Tail of stderr:\n{}\n\n\nStdout:\n{}\n\n\nStderr:\n{}\n\n\nNo profit found.",
+            tail_lines(&stderr, FAILURE_STDERR_TAIL_LINES),
+            stdout,
+            stderr
+        );
+    }
+
+    let log_contents = match soft_stopped {
+        true => {
+            eprintln!(
+                "combination {} {}, scored on what it flushed",
+                i,
+                soft_reason()
+            );
+
+            format!(
+                "{}{}.\n\n\n{}",
+                SOFT_STOP_PREFIX,
+                soft_reason(),
+                log_contents
+            )
+        }
+        false => log_contents,
+    };
+
+    logs.write(log_contents, log_path)?;
+
+    let error = match (parsed_profit, non_finite) {
+        (_, Some(value)) => {
+            eprintln!("combination {} produced a non-finite profit {}", i, value);
+
+            Some(format!("the profit was {}", value))
+        }
+        (Some(_), None) if soft_stopped => Some(soft_reason()),
+        (Some(_), None) => None,
+        (None, _) => {
+            let reason = stderr
+                .lines()
+                .rev()
+                .find(|line| !line.trim().is_empty())
+                .unwrap_or("stderr was empty");
+            eprintln!("combination {} produced no profit: {}", i, reason);
+
+            Some(tail_lines(&stderr, FAILURE_STDERR_TAIL_LINES))
+        }
+    };
+
+    Ok(Evaluation {
+        index: i,
+        parameters,
+        profit,
+        outcome: match (parsed_profit, non_finite) {
+            (_, Some(_)) => RunOutcome::NonFinite,
+            (Some(_), None) if soft_stopped => RunOutcome::SoftTimeout,
+            (Some(_), None) => RunOutcome::Success,
+            (None, _) => RunOutcome::NoProfit,
+        },
+        raw_profit: raw_profit.filter(|_| cfg.objective.is_some()),
+        baseline_delta: None,
+        profit_std,
+        duration_secs,
+        error,
+    })
+}
+
+// one run per repeat, stopping at the first that doesn't finish
+fn run_repeats(
+    i: usize,
+    parameters: &BTreeMap<String, f64>,
+    script_path: &Path,
+    stdin: Option<String>,
+    cfg: &Config,
+    runner: &dyn Runner,
+    in_flight: &InFlight,
+) -> Result<Vec<(RunOutput, f64, Option<f64>)>, WorkerError> {
+    if cfg.repeats == 1 {
+        return Ok(vec![run_once(
+            i,
+            parameters,
+            script_path,
+            stdin,
+            cfg,
+            runner,
+            in_flight,
+        )?]);
+    }
+
+    let mut runs = Vec::with_capacity(cfg.repeats);
+    let mut repeat_cfg = cfg.clone();
+
+    for repeat in 0..cfg.repeats {
+        repeat_cfg.seed = Some(repeat_seed(cfg, repeat));
+
+        let run = run_once(
+            i,
+            parameters,
+            script_path,
+            stdin.clone(),
+            &repeat_cfg,
+            runner,
+            in_flight,
+        )?;
+        let finished = run.0.exit == Exit::Finished;
+
+        runs.push(run);
+
+        if !finished {
+            break;
+        }
+    }
+
+    Ok(runs)
+}
+
+// the output, how long it took and the profit it reported, read before the
+// next repeat can overwrite the results json
+fn run_once(
+    i: usize,
+    parameters: &BTreeMap<String, f64>,
+    script_path: &Path,
+    stdin: Option<String>,
+    cfg: &Config,
+    runner: &dyn Runner,
+    in_flight: &InFlight,
+) -> Result<(RunOutput, f64, Option<f64>), WorkerError> {
+    let results_path = cfg
+        .results_json
+        .as_ref()
+        .map(|results| results_json_path(&results.path, i, script_path));
+
+    // a leftover from an earlier sweep would be taken for this run's results
+    if let Some(results_path) = &results_path {
+        let _ = fs::remove_file(results_path);
+    }
+
+    let started = Instant::now();
+    let output = runner.run(i, parameters, script_path, stdin, cfg, in_flight)?;
+    let secs = started.elapsed().as_secs_f64();
+
+    let profit = match (&cfg.results_json, &results_path) {
+        (Some(results), Some(results_path)) => read_results_profit(results_path, &results.profit),
+        _ => None,
+    }
+    .or_else(|| get_profit(&output.stdout, &cfg.profit_regex(), cfg.profit_match))
+    .map(|profit| normalize_profit(profit, cfg));
+
+    Ok((output, secs, profit))
+}
+
+fn results_json_path(template: &str, i: usize, script_path: &Path) -> PathBuf {
+    PathBuf::from(
+        template
+            .replace("{index}", &i.to_string())
+            .replace("{script}", &script_path.to_string_lossy()),
+    )
+}
+
+// None when the file, or the number at `pointer` in it, isn't there
+fn read_results_profit(path: &Path, pointer: &str) -> Option<f64> {
+    let results: serde_json::Value = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+
+    results.pointer(pointer)?.as_f64()
+}
+
+fn repeat_seed(cfg: &Config, repeat: usize) -> u64 {
+    cfg.seed.unwrap_or_default() + repeat as u64
+}
+
+fn join_repeats(
+    runs: &[(RunOutput, f64, Option<f64>)],
+    cfg: &Config,
+    stream: impl Fn(&RunOutput) -> &String,
+) -> String {
+    runs.iter()
+        .enumerate()
+        .map(|(repeat, (output, _, _))| {
+            format!(
+                "Repeat {} (seed {}):\n{}",
+                repeat,
+                repeat_seed(cfg, repeat),
+                stream(output)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn sample_std(values: &[f64]) -> f64 {
+    let mean = mean(values);
+    let squares = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>();
+
+    (squares / (values.len() - 1) as f64).sqrt()
+}
+
+fn write_plot_spec(
+    evaluations: &[Evaluation],
+    cfg: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match vega_lite_spec(evaluations, &cfg.variables) {
+        Some(spec) => create_file(
+            &serde_json::to_string_pretty(&spec)?,
+            &Path::new(&cfg.logs_dir).join(PLOT_SPEC),
+        )?,
+        None => eprintln!("no plot written, it's only drawn for sweeps over one or two variables"),
+    }
+
+    Ok(())
+}
+
+fn print_timeouts(evaluations: &[Evaluation], cfg: &Config) {
+    let timed_out = evaluations
+        .iter()
+        .filter(|e| matches!(e.outcome, RunOutcome::Timeout | RunOutcome::SoftTimeout))
+        .count();
+
+    let Some(timeout_secs) = cfg
+        .soft_timeout_secs
+        .or(cfg.kill_after_secs())
+        .filter(|_| timed_out > 0)
+    else {
+        return;
+    };
+
+    eprintln!(
+        "{} of {} combinations timed out after {}s",
+        timed_out,
+        evaluations.len(),
+        timeout_secs
+    );
+
+    let clusters = timeout_clusters(evaluations, &cfg.variables);
+
+    for cluster in &clusters {
+        eprintln!(
+            "  {} between {} and {} (swept {} to {})",
+            cluster.name, cluster.min, cluster.max, cluster.swept_min, cluster.swept_max
+        );
+    }
+
+    if !clusters.is_empty() {
+        eprintln!(
+            "the timeouts cluster in part of the grid, consider raising timeout_secs if those runs are just slow"
+        );
+    }
+}
+
+fn print_summary(best: &Best, evaluations: &[Evaluation], cfg: &Config) {
+    if let Some(note) = &cfg.note {
+        println!("\n{}", note);
+    }
+
+    println!(
+        "\nBest profit {} from combination {}{}{}:",
+        best.profit,
+        best.evaluation.index,
+        match best.evaluation.profit_std {
+            Some(std) => format!(" (mean of {} runs, std {})", cfg.repeats, std),
+            None => String::new(),
+        },
+        match best.evaluation.baseline_delta {
+            Some(delta) => format!(" ({:+} vs baseline)", delta),
+            None => String::new(),
+        }
+    );
+
+    let (script_path, log_path) = best_paths(best, cfg);
+    if let Some(script_path) = script_path {
+        println!("  script {}", script_path.display());
+    }
+    println!("  log {}", log_path.display());
+
+    for summary in parameter_summaries(&best.evaluation, evaluations, &cfg.variables) {
+        println!(
+            "  {} = {} (tried {} values from {} to {}){}",
+            display_name(&summary.name, cfg),
+            summary.best,
+            summary.count,
+            summary.min,
+            summary.max,
+            if summary.at_boundary() {
+                ", best at the boundary, consider widening the range"
+            } else {
+                ""
+            }
+        );
+    }
+
+    if let Some(end) = order_end(&best.evaluation, evaluations) {
+        eprintln!(
+            "warning: the best is the {} combination run, the optimum may be outside the searched ranges, consider widening them",
+            match end {
+                OrderEnd::First => "first",
+                OrderEnd::Last => "last",
+            }
+        );
+    }
+
+    if cfg.strategy != Strategy::Grid {
+        print_coverage(evaluations, cfg);
+    }
+
+    for peak in sharp_peaks(&best.evaluation, evaluations, &cfg.variables) {
+        eprintln!(
+            "warning: the best sits on a sharp peak at {} = {}, the profit drops {} and {} either side, it may be overfit",
+            display_name(&peak.name, cfg),
+            peak.value,
+            peak.left_drop,
+            peak.right_drop
+        );
+    }
+}
+
+fn print_coverage(evaluations: &[Evaluation], cfg: &Config) {
+    match min_distance(evaluations, &cfg.variables) {
+        Some(distance) => println!(
+            "\nCoverage of {} samples, the closest two {:.3} apart with each range scaled to 0..1:",
+            evaluations.len(),
+            distance
+        ),
+        None => println!("\nCoverage of {} samples:", evaluations.len()),
+    }
+
+    for coverage in coverage(evaluations, &cfg.variables) {
+        println!(
+            "  {} {:?} from {} to {}{}",
+            display_name(&coverage.name, cfg),
+            coverage.histogram,
+            coverage.min,
+            coverage.max,
+            match coverage.empty_bins() {
+                0 => String::new(),
+                empty => format!(
+                    ", {} of {} bins empty, consider more n_samples",
+                    empty, COVERAGE_BINS
+                ),
+            }
+        );
+    }
+}
+
+// every value in any one blocklist entry matches
+fn is_blocked(parameters: &BTreeMap<String, f64>, cfg: &Config) -> bool {
+    cfg.blocklist.iter().any(|blocked| {
+        blocked.iter().all(|(name, value)| {
+            parameters
+                .get(name)
+                .is_some_and(|v| (v - value).abs() < BLOCKLIST_TOLERANCE)
+        })
+    })
+}
+
+fn display_name(name: &str, cfg: &Config) -> String {
+    cfg.variables
+        .iter()
+        .find(|var| var.name == name)
+        .map_or_else(|| name.to_string(), |var| var.display_name())
+}
+
+// logs are counted as they're handed to the sink instead, a buffered one may
+// not have written them yet
+fn script_bytes(indices: &[usize], cfg: &Config) -> u64 {
+    indices
+        .iter()
+        .filter_map(|i| fs::metadata(get_script_path(*i, cfg)).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+// the config a slice was run with, from the copy kept with its results
+fn slice_config(config_path: &Path) -> Option<Config> {
+    let contents = read_file(&config_path.to_string_lossy()).ok()?;
+
+    serde_json::from_str(&contents).ok()
+}
+
+// slices are meant to differ in where they write, not in what they sweep
+fn check_same_sweep(input: &str, slice_cfg: Config, cfg: &Config) {
+    if sweep_hash(&slice_cfg) == sweep_hash(cfg) {
+        return;
+    }
+
+    // list only the swept fields that differ, not every path or flag the
+    // slice happened to be run with
+    let mut swept = cfg.clone();
+    swept.variables = slice_cfg.variables;
+    swept.order = slice_cfg.order;
+    swept.derived = slice_cfg.derived;
+    swept.strategy = slice_cfg.strategy;
+    swept.n_samples = slice_cfg.n_samples;
+    swept.seed = slice_cfg.seed;
+    swept.script = slice_cfg.script;
+    swept.objective = slice_cfg.objective;
+
+    eprintln!("warning: {} was run with a different sweep:", input);
+
+    for change in diff_configs(cfg, &swept) {
+        eprintln!("  {}", change);
+    }
+}
+
+fn print_log_tail(i: usize, cfg: &Config) {
+    let log_path = get_log_path(i, cfg);
+
+    match read_file(&log_path.to_string_lossy()) {
+        Ok(contents) => eprintln!(
+            "Last failing combination ({}), tail of {}:\n{}",
+            i,
+            log_path.display(),
+            tail_lines(&contents, ABORT_LOG_TAIL_LINES)
+        ),
+        Err(why) => eprintln!("couldn't read {}: {}", log_path.display(), why),
+    }
+}
+
+pub fn backtester_args(cfg: &Config, script_path: &Path) -> Vec<String> {
+    let warmup = cfg.warmup.map(|w| w.to_string()).unwrap_or_default();
+    let seed = cfg.seed.map(|s| s.to_string()).unwrap_or_default();
+
+    cfg.backtester_args
+        .iter()
+        .map(|arg| {
+            arg.replace("{script}", &script_path.to_string_lossy())
+                .replace("{round}", &cfg.round.to_string())
+                .replace("{warmup}", &warmup)
+                .replace("{seed}", &seed)
+        })
+        .collect()
+}
+
+// scripts for `ScriptDelivery::Ram` live here, under /dev/shm where it exists
+// what the backtester is given as the script for combination `i`
+pub fn delivered_script_path(i: usize, cfg: &Config) -> PathBuf {
+    match cfg.script_delivery {
+        ScriptDelivery::Disk => get_script_path(i, cfg),
+        ScriptDelivery::Ram => ram_script_dir().join(format!("script_{}.py", i)),
+        ScriptDelivery::Stdin => PathBuf::from("-"),
+    }
+}
+
+pub fn ram_script_dir() -> PathBuf {
+    let shm = Path::new("/dev/shm");
+    let base = if shm.is_dir() {
+        shm.to_path_buf()
+    } else {
+        std::env::temp_dir()
+    };
+
+    base.join(format!("grid_search-{}", std::process::id()))
+}
+
+fn run_script(
+    backtester: &Path,
+    args: &[String],
+    stdin: Option<String>,
+    i: usize,
+    parameters: &BTreeMap<String, f64>,
+    cfg: &Config,
+    in_flight: &InFlight,
+) -> Result<RunOutput, WorkerError> {
+    let mut child = Command::new(backtester)
+        .args(args)
+        .env(INDEX_ENV, i.to_string())
+        .env(PARAMETERS_ENV, serde_json::to_string(parameters)?)
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
         .stderr(Stdio::piped())
         .stdout(Stdio::piped())
-        .output()
-        .expect("failed to create subprocess");
+        .spawn()
+        .map_err(|e| format!("failed to create subprocess: {}", e))?;
+
+    if let (Some(contents), Some(mut child_stdin)) = (stdin, child.stdin.take()) {
+        // a child that exits without reading its stdin just gets a broken pipe
+        thread::spawn(move || {
+            let _ = child_stdin.write_all(contents.as_bytes());
+        });
+    }
+
+    // stderr is drained on its own thread so a chatty child can't block on a
+    // full pipe while stdout is being read
+    let mut child_stderr = child.stderr.take().unwrap();
+    let stderr_reader = thread::spawn(move || {
+        let mut buf = Vec::new();
+        child_stderr.read_to_end(&mut buf).map(|_| buf)
+    });
+
+    // stdout too, so the child can be killed if it runs past the timeout
+    let mut child_stdout = child.stdout.take().unwrap();
+    let capture_tail_lines = cfg.capture_tail_lines;
+    let stdout_reader = thread::spawn(move || -> Result<String, WorkerError> {
+        match capture_tail_lines {
+            Some(n) => read_tail(child_stdout, n),
+            None => {
+                let mut buf = Vec::new();
+                child_stdout.read_to_end(&mut buf)?;
+                Ok(String::from_utf8(buf).map_err(|_| "stdout not valid utf8")?)
+            }
+        }
+    });
+
+    in_flight.0.lock().unwrap().insert(i, Instant::now());
+    let exit = wait_for_exit(&mut child, i, cfg, in_flight);
+    in_flight.0.lock().unwrap().remove(&i);
+    let exit = exit?;
+
+    let stdout = stdout_reader
+        .join()
+        .map_err(|_| "stdout reader panicked")??;
+
+    let stderr_buf = stderr_reader
+        .join()
+        .map_err(|_| "stderr reader panicked")??;
+    let stderr = String::from_utf8(stderr_buf).map_err(|_| "stderr not valid utf8")?;
+
+    Ok(RunOutput {
+        stdout,
+        stderr,
+        exit,
+    })
+}
+
+fn wait_for_exit(
+    child: &mut Child,
+    i: usize,
+    cfg: &Config,
+    in_flight: &InFlight,
+) -> Result<Exit, WorkerError> {
+    let started = Instant::now();
+    let soft_deadline = cfg
+        .soft_timeout_secs
+        .map(|secs| started + Duration::from_secs(secs));
+    let deadline = cfg
+        .kill_after_secs()
+        .map(|secs| started + Duration::from_secs(secs));
+    let skip_file = skip_file_path(cfg);
+    let mut terminated = false;
+
+    let exit = loop {
+        if child.try_wait()?.is_some() {
+            return Ok(if terminated {
+                Exit::Terminated
+            } else {
+                Exit::Finished
+            });
+        }
+
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            break Exit::TimedOut;
+        }
+
+        if !terminated && soft_deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            terminated = terminate(child);
+        }
+
+        if skip_file.exists() && claim_skip(i, &skip_file, in_flight) {
+            break Exit::Skipped;
+        }
+
+        thread::sleep(EXIT_POLL);
+    };
+
+    // it may have exited since the check, which is fine
+    let _ = child.kill();
+    child.wait()?;
+
+    Ok(exit)
+}
+
+// asks the child to stop so it can flush partial results, false where that
+// isn't possible and it'll be killed at the hard timeout instead
+#[cfg(unix)]
+fn terminate(child: &Child) -> bool {
+    unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGTERM) == 0 }
+}
+
+#[cfg(not(unix))]
+fn terminate(_child: &Child) -> bool {
+    false
+}
+
+// creating this file skips whichever combination has been running longest
+pub fn skip_file_path(cfg: &Config) -> PathBuf {
+    Path::new(&cfg.logs_dir).join("skip")
+}
+
+// every running combination sees the skip file, only the longest running one
+// acts on it, and removing it under the lock means it's only acted on once
+fn claim_skip(i: usize, skip_file: &Path, in_flight: &InFlight) -> bool {
+    let in_flight = in_flight.0.lock().unwrap();
+
+    let longest = in_flight
+        .iter()
+        .min_by_key(|(_, started)| **started)
+        .map(|(index, _)| *index);
+
+    longest == Some(i) && fs::remove_file(skip_file).is_ok()
+}
+
+// keeps only the last `n` lines of `reader` in memory
+fn read_tail(reader: impl Read, n: usize) -> Result<String, WorkerError> {
+    let mut lines: VecDeque<String> = VecDeque::with_capacity(n);
+
+    for line in BufReader::new(reader).lines() {
+        if n == 0 {
+            continue;
+        }
+
+        if lines.len() == n {
+            lines.pop_front();
+        }
+
+        lines.push_back(line?);
+    }
 
-    *stdout = String::from_utf8(child.stdout).expect("stdout not valid utf8");
-    *stderr = String::from_utf8(child.stderr).expect("stderr not valid utf8");
+    Ok(Vec::from(lines).join("\n"))
 }
@@ -0,0 +1,35 @@
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+use crate::config::VariableConfig;
+
+pub fn new_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_os_rng(),
+    }
+}
+
+// latin hypercube: each variable's range is cut into `n_samples` equal strata
+// and every stratum is sampled exactly once, with the strata shuffled
+// independently per variable
+pub fn latin_hypercube(
+    vars: &[VariableConfig],
+    n_samples: usize,
+    rng: &mut StdRng,
+) -> Vec<Vec<f64>> {
+    let mut samples = vec![Vec::with_capacity(vars.len()); n_samples];
+
+    for var in vars {
+        let mut strata = (0..n_samples).collect::<Vec<_>>();
+        strata.shuffle(rng);
+
+        for (sample, stratum) in samples.iter_mut().zip(strata) {
+            let offset: f64 = rng.random();
+            let fraction = (stratum as f64 + offset) / n_samples as f64;
+
+            sample.push(var.start + fraction * (var.end - var.start));
+        }
+    }
+
+    samples
+}
@@ -0,0 +1,86 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Config, ScriptDelivery},
+    disk::describe_write_error,
+    evaluation::Evaluation,
+    get_log_path, get_script_path, read_file,
+    run::Best,
+};
+
+// `max_profit` and `constants` are kept at the top level so state files keep
+// the same shape as the ones written by grid_search.py
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct State {
+    pub max_profit: f64,
+    pub constants: String,
+
+    #[serde(default)]
+    pub best: Option<Evaluation>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+
+    // where the best combination's script and log were written, the script
+    // only when it's kept on disk
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script_path: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_path: Option<String>,
+}
+
+impl State {
+    pub fn new(best: &Best, cfg: &Config) -> State {
+        let (script_path, log_path) = best_paths(best, cfg);
+
+        State {
+            max_profit: best.profit,
+            constants: best.constants.clone(),
+            best: Some(best.evaluation.clone()),
+            note: cfg.note.clone(),
+            script_path: script_path.map(|p| p.to_string_lossy().into_owned()),
+            log_path: Some(log_path.to_string_lossy().into_owned()),
+        }
+    }
+}
+
+// the script is only left behind when it's delivered on disk
+pub fn best_paths(best: &Best, cfg: &Config) -> (Option<PathBuf>, PathBuf) {
+    let i = best.evaluation.index;
+
+    let script_path = match cfg.script_delivery {
+        ScriptDelivery::Disk => Some(get_script_path(i, cfg)),
+        ScriptDelivery::Ram | ScriptDelivery::Stdin => None,
+    };
+
+    (script_path, get_log_path(i, cfg))
+}
+
+pub fn load_state(fp: &str) -> Result<State, Box<dyn std::error::Error>> {
+    let contents = read_file(fp)?;
+
+    let state: State = serde_json::from_str(&contents)?;
+
+    Ok(state)
+}
+
+// a state_file in a directory that isn't there yet, e.g. a fresh
+// results/best.json, gets that directory made for it
+pub fn save_state(fp: &str, state: &State) -> Result<(), Box<dyn std::error::Error>> {
+    let path = Path::new(fp);
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|why| describe_write_error(parent, &why))?;
+    }
+
+    fs::write(path, serde_json::to_string_pretty(state)?)
+        .map_err(|why| describe_write_error(path, &why))?;
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    evaluation::{Evaluation, RunOutcome},
+    read_file,
+};
+
+// the average time a backtester command took per run on this machine, kept
+// in timing_cache so --explain can estimate a sweep without running it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Timing {
+    pub command: String,
+    pub host: String,
+    pub mean_secs: f64,
+    pub runs: usize,
+}
+
+// what a run's time depends on, the arguments keep their {script}
+// placeholder so it's the same for every combination
+pub fn command_line(backtester: &Path, cfg: &Config) -> String {
+    format!(
+        "{} {} (round {}, {:?} workers)",
+        backtester.display(),
+        cfg.backtester_args.join(" "),
+        cfg.round,
+        cfg.worker_mode
+    )
+}
+
+// None when there's no cache yet or it was timed with another command or machine
+pub fn load_timing(path: &str, command: &str) -> Option<Timing> {
+    let timing: Timing = serde_json::from_str(&read_file(path).ok()?).ok()?;
+
+    (timing.command == command && timing.host == host_name()).then_some(timing)
+}
+
+// folds a sweep's successful runs into the cached average, starting it over
+// when the command or machine changed
+pub fn record_timing(
+    path: &str,
+    command: &str,
+    evaluations: &[Evaluation],
+    repeats: usize,
+) -> Result<Option<Timing>, Box<dyn std::error::Error>> {
+    let durations = evaluations
+        .iter()
+        .filter(|e| e.outcome == RunOutcome::Success)
+        .map(|e| e.duration_secs / repeats as f64)
+        .collect::<Vec<_>>();
+
+    if durations.is_empty() {
+        return Ok(None);
+    }
+
+    let runs = durations.len() * repeats;
+    let total_secs = durations.iter().sum::<f64>() * repeats as f64;
+
+    let timing = match load_timing(path, command) {
+        Some(cached) => Timing {
+            mean_secs: (cached.mean_secs * cached.runs as f64 + total_secs)
+                / (cached.runs + runs) as f64,
+            runs: cached.runs + runs,
+            ..cached
+        },
+        None => Timing {
+            command: command.to_string(),
+            host: host_name(),
+            mean_secs: total_secs / runs as f64,
+            runs,
+        },
+    };
+
+    fs::write(path, serde_json::to_string_pretty(&timing)?)?;
+
+    Ok(Some(timing))
+}
+
+pub fn format_secs(secs: f64) -> String {
+    let secs = secs.round() as u64;
+
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+#[cfg(unix)]
+fn host_name() -> String {
+    let mut name = [0u8; 256];
+
+    if unsafe { libc::gethostname(name.as_mut_ptr().cast(), name.len()) } != 0 {
+        return String::from("unknown");
+    }
+
+    let end = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+    String::from_utf8_lossy(&name[..end]).into_owned()
+}
+
+#[cfg(not(unix))]
+fn host_name() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_else(|_| String::from("unknown"))
+}
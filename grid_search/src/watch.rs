@@ -0,0 +1,136 @@
+use std::{
+    io::{self, BufRead, Write},
+    path::Path,
+    sync::{Mutex, Once, mpsc},
+    thread,
+    time::Duration,
+};
+
+use notify::{RecursiveMode, Watcher};
+
+use crate::{
+    config::{Config, diff_configs, parse_config},
+    count_combinations,
+};
+
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+pub enum WatchEvent {
+    Changed,
+    Confirmed,
+}
+
+// where the stdin reader sends the enters it reads, the latest watch_config's channel
+static CONFIRMATIONS: Mutex<Option<mpsc::Sender<WatchEvent>>> = Mutex::new(None);
+static STDIN_READER: Once = Once::new();
+
+// blocks until the user presses enter, reporting every change made to the
+// config in the meantime, then returns the latest valid config
+pub fn watch_config(config_path: &str) -> Result<Config, Box<dyn std::error::Error>> {
+    let (tx, rx) = mpsc::channel();
+    confirm_on_enter(tx.clone());
+
+    watch_until(config_path, tx, rx, &mut io::stdout())
+}
+
+// one reader for the whole process, rather than one per config watched that
+// would each hold on to stdin after their config had run
+fn confirm_on_enter(tx: mpsc::Sender<WatchEvent>) {
+    *CONFIRMATIONS.lock().unwrap() = Some(tx);
+
+    STDIN_READER.call_once(|| {
+        thread::spawn(|| {
+            let mut line = String::new();
+
+            while io::stdin().lock().read_line(&mut line).is_ok_and(|n| n > 0) {
+                if let Some(tx) = CONFIRMATIONS.lock().unwrap().as_ref() {
+                    let _ = tx.send(WatchEvent::Confirmed);
+                }
+
+                line.clear();
+            }
+        });
+    });
+}
+
+// reports changes to the config into `out` until a `Confirmed` arrives on
+// `rx`, `tx` being where the file watcher sends its changes
+pub fn watch_until(
+    config_path: &str,
+    tx: mpsc::Sender<WatchEvent>,
+    rx: mpsc::Receiver<WatchEvent>,
+    out: &mut dyn Write,
+) -> Result<Config, Box<dyn std::error::Error>> {
+    let path = Path::new(config_path).canonicalize()?;
+    let dir = path.parent().ok_or("config has no parent directory")?;
+
+    let watched = path.clone();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(event) = res {
+            if event.kind.is_access() || !event.paths.contains(&watched) {
+                return;
+            }
+
+            let _ = tx.send(WatchEvent::Changed);
+        }
+    })?;
+
+    // watching the directory rather than the file survives editors that
+    // save by replacing the file
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+    let mut current = parse_config(config_path)?;
+    print_summary(&current, out)?;
+
+    loop {
+        match rx.recv()? {
+            WatchEvent::Confirmed => return Ok(current),
+            WatchEvent::Changed => {
+                // an enter while the saves settle still runs, once they're read
+                let confirmed = loop {
+                    match rx.recv_timeout(DEBOUNCE) {
+                        Ok(WatchEvent::Changed) => continue,
+                        Ok(WatchEvent::Confirmed) => break true,
+                        Err(_) => break false,
+                    }
+                };
+
+                match parse_config(config_path) {
+                    Ok(new) => {
+                        report_changes(&current, &new, out)?;
+                        current = new;
+                    }
+                    Err(why) => eprintln!("{} is invalid: {}", config_path, why),
+                }
+
+                if confirmed {
+                    return Ok(current);
+                }
+            }
+        }
+    }
+}
+
+fn report_changes(old: &Config, new: &Config, out: &mut dyn Write) -> io::Result<()> {
+    let changes = diff_configs(old, new);
+
+    if changes.is_empty() {
+        writeln!(out, "\nConfig saved with no changes")?;
+    } else {
+        writeln!(out, "\nConfig changed:")?;
+
+        for change in changes {
+            writeln!(out, "  {}", change)?;
+        }
+    }
+
+    print_summary(new, out)
+}
+
+fn print_summary(cfg: &Config, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "{} combinations, press enter to run or keep editing",
+        count_combinations(cfg)
+    )
+}
@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+
+use grid_search::{
+    analysis::{
+        COVERAGE_BINS, OrderEnd, TieBreak, break_tie, coverage, min_distance, order_end, plateau,
+        sharp_peaks,
+    },
+    config::{Config, ConfigBuilder, Strategy},
+    evaluation::{Evaluation, RunOutcome, parse_constants},
+    get_constant_strings,
+};
+
+fn config() -> Config {
+    ConfigBuilder::new()
+        .add_variable("x", 0., 5., 1.)
+        .build()
+        .unwrap()
+}
+
+fn evaluations(profits: &[f64]) -> Vec<Evaluation> {
+    profits
+        .iter()
+        .enumerate()
+        .map(|(index, profit)| Evaluation {
+            index,
+            parameters: BTreeMap::from([(String::from("x"), index as f64)]),
+            profit: Some(*profit),
+            outcome: RunOutcome::Success,
+            raw_profit: None,
+            baseline_delta: None,
+            profit_std: None,
+            duration_secs: 0.,
+            error: None,
+        })
+        .collect()
+}
+
+#[test]
+fn spike_is_flagged_and_smooth_peak_is_not() {
+    let cfg = config();
+
+    let spiky = evaluations(&[100., 105., 400., 102., 98.]);
+    let peaks = sharp_peaks(&spiky[2], &spiky, &cfg.variables);
+    assert_eq!(peaks.len(), 1);
+    assert_eq!(peaks[0].name, "x");
+    assert_eq!(peaks[0].value, 2.);
+
+    let smooth = evaluations(&[100., 300., 400., 310., 120.]);
+    assert!(sharp_peaks(&smooth[2], &smooth, &cfg.variables).is_empty());
+}
+
+#[test]
+fn best_at_either_end_of_the_order_is_flagged() {
+    let rising = evaluations(&[100., 200., 300., 400., 500.]);
+    assert_eq!(order_end(&rising[4], &rising), Some(OrderEnd::Last));
+
+    let falling = evaluations(&[500., 400., 300., 200., 100.]);
+    assert_eq!(order_end(&falling[0], &falling), Some(OrderEnd::First));
+
+    let peaked = evaluations(&[100., 300., 400., 310., 120.]);
+    assert_eq!(order_end(&peaked[2], &peaked), None);
+
+    // a single run is both ends, and says nothing about the range
+    let single = evaluations(&[100.]);
+    assert_eq!(order_end(&single[0], &single), None);
+}
+
+#[test]
+fn coverage_of_a_seeded_sample_stays_in_bounds() {
+    let cfg = ConfigBuilder::new()
+        .add_variable("x", 0., 5., 1.)
+        .add_variable("y", -2., 2., 0.5)
+        .strategy(Strategy::Lhs, 40)
+        .seed(11)
+        .build()
+        .unwrap();
+
+    let evaluations = get_constant_strings(&cfg)
+        .iter()
+        .enumerate()
+        .map(|(index, constants)| Evaluation {
+            index,
+            parameters: parse_constants(constants),
+            ..evaluations(&[0.]).remove(0)
+        })
+        .collect::<Vec<_>>();
+
+    let coverage = coverage(&evaluations, &cfg.variables);
+    assert_eq!(coverage.len(), 2);
+
+    for (var, coverage) in cfg.variables.iter().zip(&coverage) {
+        assert!(coverage.min >= var.start && coverage.max <= var.end);
+        assert_eq!(coverage.histogram.len(), COVERAGE_BINS);
+        assert_eq!(coverage.histogram.iter().sum::<usize>(), 40);
+
+        // a latin hypercube puts 4 of 40 samples in each tenth of the range
+        assert_eq!(coverage.empty_bins(), 0);
+    }
+
+    let distance = min_distance(&evaluations, &cfg.variables).unwrap();
+    assert!(distance > 0. && distance < 1.);
+}
+
+#[test]
+fn equal_profits_pick_the_same_winner_in_any_order() {
+    let cfg = config();
+    let evaluations = evaluations(&[90., 120., 100., 120., 120.]);
+
+    for rule in [TieBreak::First, TieBreak::Central, TieBreak::Simplest] {
+        let winner = |order: &[usize]| {
+            let shuffled = order
+                .iter()
+                .map(|i| evaluations[*i].clone())
+                .collect::<Vec<_>>();
+
+            break_tie(&plateau(&shuffled, 0.), &cfg.variables, rule)
+                .unwrap()
+                .index
+        };
+
+        let expected = winner(&[0, 1, 2, 3, 4]);
+        assert_eq!(winner(&[4, 3, 2, 1, 0]), expected);
+        assert_eq!(winner(&[3, 0, 4, 2, 1]), expected);
+
+        // first goes to the lowest index, and x = 3 is the most central of the three
+        match rule {
+            TieBreak::First => assert_eq!(expected, 1),
+            TieBreak::Central => assert_eq!(expected, 3),
+            TieBreak::Simplest => assert_eq!(expected, 1),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+// timings rather than tests, run with
+// cargo test --release --test bench -- --ignored --nocapture
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use grid_search::{
+    config::{Config, ConfigBuilder, ScriptDelivery},
+    create_or_clean_logs_dir, get_constant_strings,
+    run::{Exit, InFlight, RunOutput, Runner, WorkerError, run_all_with},
+};
+
+const COMBINATIONS: usize = 2000;
+const THREADS: usize = 8;
+
+// reads the script the way a backtester would, so each delivery pays for
+// getting it there, and returns straight away with `log_lines` of output
+struct ReadingRunner {
+    log_lines: usize,
+}
+
+impl Runner for ReadingRunner {
+    fn run(
+        &self,
+        _i: usize,
+        _parameters: &BTreeMap<String, f64>,
+        script_path: &Path,
+        stdin: Option<String>,
+        _cfg: &Config,
+        _in_flight: &InFlight,
+    ) -> Result<RunOutput, WorkerError> {
+        let script = match stdin {
+            Some(script) => script,
+            None => fs::read_to_string(script_path)?,
+        };
+
+        Ok(RunOutput {
+            stdout: format!(
+                "{}Total profit: {}\n",
+                "KELP: 1,234 at 2,000\n".repeat(self.log_lines),
+                script.len()
+            ),
+            stderr: String::new(),
+            exit: Exit::Finished,
+        })
+    }
+}
+
+fn bench_dir(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("grid_search-bench-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    // about the size of a real trader
+    let body = "    def run(self, state):\n        pass\n".repeat(1000);
+    fs::write(
+        dir.join("trader.py"),
+        format!("# start\nx = 0\ny = 0\n# end\n\nclass Trader:\n{}", body),
+    )
+    .unwrap();
+
+    dir
+}
+
+// runs/s for `runner` over COMBINATIONS combinations with `cfg` tweaked by `tweak`
+fn runs_per_sec(dir: &Path, runner: &dyn Runner, tweak: impl FnOnce(&mut Config)) -> f64 {
+    let mut cfg = ConfigBuilder::new()
+        .script(dir.join("trader.py").to_str().unwrap())
+        .logs_dir(dir.join("out").to_str().unwrap())
+        .add_variable("x", 0., 40., 1.)
+        .add_variable_count("y", 0., 1., COMBINATIONS / 40)
+        .build()
+        .unwrap();
+    tweak(&mut cfg);
+
+    let constant_strings = get_constant_strings(&cfg);
+    assert_eq!(constant_strings.len(), COMBINATIONS);
+    create_or_clean_logs_dir(&cfg, constant_strings.len());
+
+    let start = Instant::now();
+    run_all_with(&cfg, THREADS, runner).unwrap().unwrap();
+
+    COMBINATIONS as f64 / start.elapsed().as_secs_f64()
+}
+
+#[test]
+#[ignore]
+fn script_delivery() {
+    let dir = bench_dir("delivery");
+
+    for delivery in [
+        ScriptDelivery::Disk,
+        ScriptDelivery::Ram,
+        ScriptDelivery::Stdin,
+    ] {
+        let rate = runs_per_sec(&dir, &ReadingRunner { log_lines: 0 }, |cfg| {
+            cfg.script_delivery = delivery
+        });
+        println!("{:?}: {:.0} runs/s", delivery, rate);
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+#[ignore]
+fn write_buffer() {
+    let dir = bench_dir("write-buffer");
+
+    // logs about the size of a day's backtest
+    let runner = ReadingRunner { log_lines: 2000 };
+
+    for write_buffer in [0, 64] {
+        let rate = runs_per_sec(&dir, &runner, |cfg| cfg.write_buffer = write_buffer);
+        println!("write_buffer {}: {:.0} runs/s", write_buffer, rate);
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
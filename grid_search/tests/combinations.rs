@@ -0,0 +1,167 @@
+use grid_search::{
+    combinations_from,
+    config::{ConfigBuilder, Slice, validate_config},
+    count_combinations,
+    evaluation::parse_constants,
+    format_constants, get_constant_strings,
+};
+
+#[test]
+fn inactive_variables_collapse_to_one_value() {
+    // mode 0 is passive and mode 1 aggressive, size only matters when aggressive
+    let cfg = ConfigBuilder::new()
+        .add_variable("mode", 0., 2., 1.)
+        .add_variable("size", 1., 4., 1.)
+        .add_variable("edge", 0., 2., 1.)
+        .active_when("size", "mode", &[1.])
+        .build()
+        .unwrap();
+
+    let combinations = get_constant_strings(&cfg);
+
+    let passive = combinations
+        .iter()
+        .filter(|c| c.starts_with("mode = 0\n"))
+        .collect::<Vec<_>>();
+    assert_eq!(passive.len(), 2);
+    assert!(passive.iter().all(|c| c.contains("size = 1\n")));
+
+    // 2 passive (edge only) and 3 sizes by 2 edges aggressive
+    assert_eq!(combinations.len(), 8);
+    assert_eq!(count_combinations(&cfg), 8);
+}
+
+#[test]
+fn format_constants_matches_the_sweep() {
+    let cfg = ConfigBuilder::new()
+        .add_variable("edge", 0.1, 0.4, 0.1)
+        .add_variable_count("window", 5., 20., 4)
+        .add_derived("ratio", "edge / window")
+        .build()
+        .unwrap();
+
+    let combinations = get_constant_strings(&cfg);
+    assert_eq!(combinations.len(), 12);
+
+    for constants in combinations {
+        // the parsed values are rounded to what was printed, and print the same again
+        let parameters = parse_constants(&constants);
+
+        assert_eq!(format_constants(&parameters, &cfg).unwrap(), constants);
+    }
+}
+
+#[test]
+fn order_changes_the_nesting_but_not_the_set() {
+    let mut cfg = ConfigBuilder::new()
+        .add_variable("x", 0., 3., 1.)
+        .add_variable("y", 0., 2., 1.)
+        .build()
+        .unwrap();
+    let declared = get_constant_strings(&cfg);
+
+    cfg.order = vec![String::from("y"), String::from("x")];
+    validate_config(&cfg).unwrap();
+    let reordered = get_constant_strings(&cfg);
+
+    // x changes fastest now, and the constants keep their declared order
+    let points = reordered
+        .iter()
+        .map(|c| {
+            let parameters = parse_constants(c);
+            (parameters["x"], parameters["y"])
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        points,
+        [(0., 0.), (1., 0.), (2., 0.), (0., 1.), (1., 1.), (2., 1.)]
+    );
+    assert!(reordered[0].starts_with("x = "));
+
+    let mut sorted = reordered.clone();
+    sorted.sort();
+    let mut expected = declared.clone();
+    expected.sort();
+    assert_eq!(sorted, expected);
+
+    // decoding an index agrees with the nested loops
+    assert_eq!(
+        combinations_from(&cfg, 2).collect::<Vec<_>>(),
+        reordered[2..]
+    );
+
+    cfg.order.pop();
+    let err = validate_config(&cfg).unwrap_err();
+    assert!(err.to_string().contains("missing variable x"), "{}", err);
+}
+
+#[test]
+fn count_saturates_past_what_can_be_enumerated() {
+    // 10^21 combinations is more than a usize holds but still counted exactly
+    let mut builder = ConfigBuilder::new();
+    for name in ["a", "b", "c"] {
+        builder = builder.add_variable(name, 0., 1e7, 1.);
+    }
+    assert_eq!(
+        count_combinations(&builder.build().unwrap()),
+        10u128.pow(21)
+    );
+
+    // and 10^280 more than a u128 does
+    let mut builder = ConfigBuilder::new();
+    for i in 0..40 {
+        builder = builder.add_variable(&format!("v{}", i), 0., 1e7, 1.);
+    }
+    assert_eq!(count_combinations(&builder.build().unwrap()), u128::MAX);
+}
+
+#[test]
+fn decoding_stops_at_the_last_combination() {
+    let cfg = ConfigBuilder::new()
+        .add_variable("x", 0., 3., 1.)
+        .add_variable("y", 0., 2., 1.)
+        .build()
+        .unwrap();
+    let count = count_combinations(&cfg) as usize;
+    let combinations = get_constant_strings(&cfg);
+
+    assert_eq!(
+        combinations_from(&cfg, count - 1).collect::<Vec<_>>(),
+        [combinations.last().unwrap().clone()]
+    );
+    assert_eq!(combinations_from(&cfg, count).count(), 0);
+}
+
+#[test]
+fn shards_together_cover_every_index_once() {
+    let cfg = ConfigBuilder::new()
+        .add_variable("x", 0., 5., 1.)
+        .add_variable("y", 0., 3., 1.)
+        .build()
+        .unwrap();
+    let count = get_constant_strings(&cfg).len();
+
+    for shards in [2, 3, 7] {
+        let mut runs = vec![0; count];
+
+        for index in 0..shards {
+            let shard = Slice::Shard {
+                index,
+                count: shards,
+            };
+
+            for (i, runs) in runs.iter_mut().enumerate() {
+                if shard.contains(i) {
+                    *runs += 1;
+                }
+            }
+        }
+
+        assert!(
+            runs.iter().all(|runs| *runs == 1),
+            "{} shards: {:?}",
+            shards,
+            runs
+        );
+    }
+}
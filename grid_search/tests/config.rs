@@ -0,0 +1,103 @@
+use std::path::Path;
+
+use grid_search::config::{ConfigBuilder, parse_config, validate_config};
+
+fn fixture(name: &str) -> String {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[test]
+fn typo_in_a_derived_expr_is_caught_at_load() {
+    let err = ConfigBuilder::new()
+        .add_variable("take_width", 1., 3., 1.)
+        .add_variable("window", 5., 20., 5.)
+        .add_derived("ratio", "take_widht / window")
+        .build()
+        .unwrap_err();
+
+    assert!(err.to_string().contains("uses take_widht"), "{}", err);
+}
+
+#[test]
+fn typo_in_the_objective_is_caught_at_load() {
+    let mut cfg = ConfigBuilder::new()
+        .add_variable("take_width", 1., 3., 1.)
+        .build()
+        .unwrap();
+    cfg.objective = Some(String::from("profit - 10 * take_widht"));
+
+    let err = validate_config(&cfg).unwrap_err();
+    assert!(err.to_string().contains("uses take_widht"), "{}", err);
+
+    cfg.objective = Some(String::from("profit - 10 * take_width"));
+    validate_config(&cfg).unwrap();
+}
+
+#[test]
+fn min_success_rate_is_a_fraction() {
+    let mut cfg = ConfigBuilder::new()
+        .add_variable("take_width", 1., 3., 1.)
+        .build()
+        .unwrap();
+
+    for rate in [-0.1, 1.5, f64::NAN] {
+        cfg.min_success_rate = Some(rate);
+        let err = validate_config(&cfg).unwrap_err();
+        assert!(err.to_string().contains("min_success_rate"), "{}", err);
+    }
+
+    for rate in [0., 0.8, 1.] {
+        cfg.min_success_rate = Some(rate);
+        validate_config(&cfg).unwrap();
+    }
+}
+
+#[test]
+fn whole_values_are_written_without_decimals() {
+    let cfg = ConfigBuilder::new()
+        .add_variable("window", 0., 30., 10.)
+        .add_variable("edge", 0., 1., 0.1)
+        .build()
+        .unwrap();
+    let (window, edge) = (&cfg.variables[0], &cfg.variables[1]);
+
+    assert_eq!(window.format_value(20.), "20");
+    assert_eq!(window.format_value(-0.), "0");
+    assert_eq!(edge.format_value(0.25), "0.250");
+
+    // 0.1 * 3 isn't quite 0.3, and 0.1 stepped ten times isn't quite 1
+    assert_eq!(edge.format_value(0.1 * 3.), "0.300");
+    assert_eq!(edge.format_value((0..10).map(|_| 0.1).sum()), "1");
+
+    // asking for decimals keeps them
+    let mut window = window.clone();
+    window.digits = Some(2);
+    assert_eq!(window.format_value(20.), "20.00");
+}
+
+#[test]
+fn config_strings_take_environment_variables() {
+    // only this test reads GRID_SEARCH_DATA_DIR, and GRID_SEARCH_LOGS_DIR is never set
+    unsafe { std::env::set_var("GRID_SEARCH_DATA_DIR", "/data/prosperity") };
+
+    let cfg = parse_config(&fixture("interpolated.json")).unwrap();
+
+    assert_eq!(cfg.script, "/data/prosperity/trader.py");
+    assert_eq!(cfg.logs_dir, "out");
+    assert_eq!(cfg.backtester_args[3], "/data/prosperity/data");
+}
+
+#[test]
+fn unset_environment_variables_without_a_default_are_an_error() {
+    let err = parse_config(&fixture("interpolated_missing.json")).unwrap_err();
+
+    assert!(
+        err.to_string().contains("${GRID_SEARCH_MISSING_DIR}"),
+        "{}",
+        err
+    );
+}
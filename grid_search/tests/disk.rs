@@ -0,0 +1,34 @@
+use std::{io, path::Path};
+
+use grid_search::disk::{describe_write_error, is_disk_full};
+
+#[test]
+fn a_full_disk_gets_the_friendly_message() {
+    let path = Path::new("out/0-99/logs/log_3.txt");
+
+    let full = io::Error::from(io::ErrorKind::StorageFull);
+    assert!(is_disk_full(&full));
+    assert_eq!(
+        describe_write_error(path, &full),
+        "the disk holding out/0-99/logs/log_3.txt is full, free up space or point logs_dir somewhere else and rerun"
+    );
+
+    let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+    assert!(!is_disk_full(&denied));
+    assert!(
+        describe_write_error(path, &denied)
+            .starts_with("couldn't write to out/0-99/logs/log_3.txt: ")
+    );
+}
+
+// /dev/full fails every write with ENOSPC, as a filled up disk would
+#[cfg(target_os = "linux")]
+#[test]
+fn writing_to_dev_full_is_a_full_disk() {
+    let path = Path::new("/dev/full");
+    let err = std::fs::write(path, "Total profit: 1500\n").unwrap_err();
+
+    assert_eq!(err.raw_os_error(), Some(libc::ENOSPC));
+    assert!(is_disk_full(&err));
+    assert!(describe_write_error(path, &err).starts_with("the disk holding /dev/full is full"));
+}
@@ -0,0 +1,1679 @@
+#![cfg(unix)]
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    ops::Deref,
+    os::unix::fs::{PermissionsExt, symlink},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use grid_search::{
+    archive::archive_sweep,
+    budget::BudgetPolicy,
+    config::{
+        Config, ConfigBuilder, CsvQuote, ParseFailure, PreCommand, ProfitMatch, ResultsJson,
+        VariableConfig, WorkerMode, validate_config,
+    },
+    count_combinations, create_or_clean_logs_dir,
+    cursor::{load_cursor, save_cursor},
+    evaluation::{Evaluation, RunOutcome},
+    get_log_path,
+    manifest::{load_evaluations, load_manifests},
+    run::{
+        Backtester, Best, ConstantsHook, Exit, InFlight, RunOutput, Runner, WorkerError,
+        run_all_with, skip_file_path,
+    },
+    state::load_state,
+    timing::Timing,
+};
+
+const TEMPLATE: &str = "import math\n\n# start\nx = 0\ny = 0\n# end\n\nclass Trader:\n    pass\n";
+
+fn fake_backtester() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fake_backtester.sh")
+}
+
+fn fake() -> Backtester {
+    Backtester {
+        path: fake_backtester(),
+    }
+}
+
+// a fresh directory per test, holding the template, removed once the test is done
+struct Scratch(PathBuf);
+
+impl Deref for Scratch {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn scratch_dir(name: &str) -> Scratch {
+    let dir = std::env::temp_dir().join(format!("grid_search-{}-{}", name, std::process::id()));
+
+    if dir.exists() {
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("trader.py"), TEMPLATE).unwrap();
+
+    Scratch(dir)
+}
+
+fn config(dir: &Path) -> Config {
+    ConfigBuilder::new()
+        .script(dir.join("trader.py").to_str().unwrap())
+        .logs_dir(dir.join("out").to_str().unwrap())
+        .state_file(dir.join("best.json").to_str().unwrap())
+        .add_variable("x", 0., 1., 0.5)
+        .add_variable("y", 0., 4., 1.)
+        .build()
+        .unwrap()
+}
+
+fn variable(name: &str, start: f64, end: f64, step: f64) -> VariableConfig {
+    ConfigBuilder::new()
+        .add_variable(name, start, end, step)
+        .build()
+        .unwrap()
+        .variables
+        .remove(0)
+}
+
+fn read_manifest(dir: &Path) -> BTreeMap<usize, Evaluation> {
+    let contents = fs::read_to_string(dir.join("out/0-99/index.json")).unwrap();
+
+    serde_json::from_str(&contents).unwrap()
+}
+
+// what a sweep left behind, its evaluations empty if it failed before
+// writing any manifests
+struct Swept {
+    cfg: Config,
+    result: Result<Option<Best>, String>,
+    evaluations: BTreeMap<usize, Evaluation>,
+}
+
+impl Swept {
+    fn best(&self) -> &Best {
+        self.result.as_ref().unwrap().as_ref().unwrap()
+    }
+
+    fn err(&self) -> &str {
+        self.result.as_ref().unwrap_err()
+    }
+}
+
+// the setup most tests share: `config` in `dir` changed by `tweak`, run over
+// 4 threads from a clean logs_dir, kept as it is when resuming like main does
+fn sweep(dir: &Path, runner: &dyn Runner, tweak: impl FnOnce(&mut Config)) -> Swept {
+    sweep_over(dir, 4, runner, tweak)
+}
+
+fn sweep_over(
+    dir: &Path,
+    threads: usize,
+    runner: &dyn Runner,
+    tweak: impl FnOnce(&mut Config),
+) -> Swept {
+    let mut cfg = config(dir);
+    tweak(&mut cfg);
+
+    if !cfg.resume {
+        create_or_clean_logs_dir(&cfg, count_combinations(&cfg) as usize);
+    }
+
+    let result = run_all_with(&cfg, threads, runner).map_err(|e| e.to_string());
+    let evaluations = load_manifests(&cfg.logs_dir)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|e| (e.index, e))
+        .collect();
+
+    Swept {
+        cfg,
+        result,
+        evaluations,
+    }
+}
+
+#[test]
+fn run_all_finds_the_best_combination() {
+    let dir = scratch_dir("run-all");
+    let swept = sweep(&dir, &fake(), |_| {});
+    let best = swept.best();
+
+    assert_eq!(best.profit, 1500.);
+    assert_eq!(best.evaluation.parameters["x"], 0.5);
+    assert_eq!(best.evaluation.parameters["y"], 2.);
+
+    let state = load_state(dir.join("best.json").to_str().unwrap()).unwrap();
+    assert_eq!(state.max_profit, 1500.);
+    assert_eq!(state.constants, best.constants);
+
+    assert_eq!(swept.evaluations.len(), 8);
+    assert!(
+        swept
+            .evaluations
+            .values()
+            .all(|e| e.outcome == RunOutcome::Success)
+    );
+
+    let script = fs::read_to_string(dir.join("out/0-99/scripts/script_0.py")).unwrap();
+    assert!(script.contains("# start\nx = 0\ny = 0\n# end"));
+    assert!(script.ends_with("class Trader:\n    pass\n"));
+}
+
+// runs the binary on a config with only the fake reachable as prosperity3bt,
+// even if the real one is installed
+fn run_binary(dir: &Path, cfg: &Config, args: &[&str]) -> std::process::Output {
+    let cfg_path = dir.join("config.json");
+    fs::write(&cfg_path, serde_json::to_string(cfg).unwrap()).unwrap();
+
+    run_configs(dir, &[cfg_path], args)
+}
+
+fn run_configs(dir: &Path, cfg_paths: &[PathBuf], args: &[&str]) -> std::process::Output {
+    let bin_dir = dir.join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    if !bin_dir.join("prosperity3bt").exists() {
+        symlink(fake_backtester(), bin_dir.join("prosperity3bt")).unwrap();
+    }
+
+    let mut command = Command::new(env!("CARGO_BIN_EXE_grid_search"));
+    for cfg_path in cfg_paths {
+        command.arg("--config").arg(cfg_path);
+    }
+
+    command
+        .args(args)
+        .env("PATH", format!("{}:/usr/bin:/bin", bin_dir.display()))
+        .env_remove("PROSPERITY3BT")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn binary_runs_a_config_file() {
+    let dir = scratch_dir("binary");
+    let output = run_binary(&dir, &config(&dir), &[]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Best profit 1500 from combination"));
+
+    let state = load_state(dir.join("best.json").to_str().unwrap()).unwrap();
+    assert_eq!(state.max_profit, 1500.);
+    assert_eq!(read_manifest(&dir).len(), 8);
+}
+
+#[test]
+fn backtester_is_found_through_the_env_var() {
+    let dir = scratch_dir("env-var");
+    let cfg_path = dir.join("config.json");
+    fs::write(&cfg_path, serde_json::to_string(&config(&dir)).unwrap()).unwrap();
+
+    // nowhere on PATH or the default search paths has a prosperity3bt
+    let output = Command::new(env!("CARGO_BIN_EXE_grid_search"))
+        .arg("--config")
+        .arg(&cfg_path)
+        .env("PATH", "/usr/bin:/bin")
+        .env("HOME", &*dir)
+        .env_remove("VIRTUAL_ENV")
+        .env("PROSPERITY3BT", fake_backtester())
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Best profit 1500"));
+}
+
+#[test]
+fn batch_keeps_going_past_a_failing_config() {
+    let dir = scratch_dir("batch");
+
+    let mut broken = config(&dir);
+    broken.script = dir.join("missing.py").to_str().unwrap().to_string();
+
+    // the two x.json share a stem, so their outputs need more than it to tell apart
+    let mut cfg_paths = Vec::new();
+    for (path, cfg) in [
+        ("configs/a/x.json", config(&dir)),
+        ("configs/b/x.json", config(&dir)),
+        ("configs/broken.json", broken),
+    ] {
+        let cfg_path = dir.join(path);
+        fs::create_dir_all(cfg_path.parent().unwrap()).unwrap();
+        fs::write(&cfg_path, serde_json::to_string(&cfg).unwrap()).unwrap();
+        cfg_paths.push(cfg_path);
+    }
+    let output = run_configs(&dir, &cfg_paths, &[]);
+
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Leaderboard:"), "{}", stdout);
+    assert!(stdout.contains("broken.json: failed, "), "{}", stdout);
+
+    for name in ["a-x", "b-x"] {
+        let state_file = dir.join("out").join(name).join("best.json");
+        let state = load_state(state_file.to_str().unwrap()).unwrap();
+        assert_eq!(state.max_profit, 1500.);
+    }
+    assert!(!dir.join("out/x").exists());
+
+    // a pattern nothing matches is an error rather than a panic
+    let pattern = dir.join("configs/*.toml");
+    let output = run_configs(&dir, &[pattern], &[]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no config files match"), "{}", stderr);
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+}
+
+#[test]
+fn parse_test_shows_what_each_config_reads() {
+    let dir = scratch_dir("parse-test");
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/backtest_output.txt");
+
+    let mut last = config(&dir);
+    last.profit_match = ProfitMatch::Last;
+    let mut kelp = config(&dir);
+    kelp.profit_pattern = String::from(r"KELP: ([\d,]+)");
+    kelp.profit_match = ProfitMatch::Sum;
+    let mut sharpe = config(&dir);
+    sharpe.profit_pattern = String::from(r"Sharpe: ([\d.]+)");
+
+    let mut cfg_paths = Vec::new();
+    for (name, cfg) in [
+        ("first", config(&dir)),
+        ("last", last),
+        ("kelp", kelp),
+        ("sharpe", sharpe),
+    ] {
+        let cfg_path = dir.join(format!("{}.json", name));
+        fs::write(&cfg_path, serde_json::to_string(&cfg).unwrap()).unwrap();
+        cfg_paths.push(cfg_path);
+    }
+
+    let output = run_configs(
+        &dir,
+        &cfg_paths,
+        &["parse-test", "--input", fixture.to_str().unwrap()],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let profits = stdout
+        .lines()
+        .filter(|line| line.starts_with("  "))
+        .map(str::trim)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        profits,
+        [
+            "profit: 3067",
+            "profit: 5967",
+            "profit: 2254",
+            "no profit found"
+        ],
+        "{}",
+        stdout
+    );
+}
+
+// what the binary printed to stderr when it refused to run with `args`
+fn refused(dir: &Path, args: &[&str]) -> String {
+    let output = run_binary(dir, &config(dir), args);
+    assert!(!output.status.success(), "{:?} ran", args);
+    assert!(!dir.join("out").exists(), "{:?} ran", args);
+
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn command_line_overrides_are_validated() {
+    let dir = scratch_dir("overrides");
+
+    let stderr = refused(&dir, &["--max-runs", "0"]);
+    assert!(stderr.contains("max_runs must be at least 1"), "{}", stderr);
+
+    for delimiter in ["\"", "é"] {
+        let stderr = refused(&dir, &["--csv-delimiter", delimiter]);
+        assert!(
+            stderr.contains("csv_delimiter must be a single ascii character"),
+            "{}",
+            stderr
+        );
+    }
+
+    let stderr = refused(&dir, &["--append"]);
+    assert!(
+        stderr.contains("append_results needs a results_csv"),
+        "{}",
+        stderr
+    );
+
+    let stderr = refused(&dir, &["--min-success-rate", "1.5"]);
+    assert!(
+        stderr.contains("min_success_rate must be a fraction"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn too_few_successes_fail_the_sweep() {
+    let dir = scratch_dir("min-successes");
+
+    let output = run_binary(&dir, &config(&dir), &["--min-successes", "8"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // every run finishes, but none of them with a profit the pattern finds
+    let mut cfg = config(&dir);
+    cfg.profit_pattern = String::from(r"Sharpe: (\d+)");
+
+    let output = run_binary(&dir, &cfg, &["--min-successes", "1"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("only 0 of 8 combinations produced a profit, min_successes is 1"),
+        "{}",
+        stderr
+    );
+
+    let output = run_binary(&dir, &cfg, &["--min-success-rate", "0.5"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("min_success_rate of 0.5"));
+}
+
+fn archive_listing(path: &Path) -> Vec<String> {
+    let mut archive =
+        tar::Archive::new(flate2::read::GzDecoder::new(fs::File::open(path).unwrap()));
+
+    archive
+        .entries()
+        .unwrap()
+        .map(|entry| {
+            entry
+                .unwrap()
+                .path()
+                .unwrap()
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect()
+}
+
+#[test]
+fn archive_bundles_a_finished_sweep() {
+    let dir = scratch_dir("archive");
+    let cfg = sweep(&dir, &fake(), |cfg| {
+        cfg.results_csv = Some(dir.join("results.csv").to_str().unwrap().to_string());
+    })
+    .cfg;
+
+    let archive = dir.join("sweep.tar.gz");
+    archive_sweep(&cfg, &archive, false).unwrap();
+    let listing = archive_listing(&archive);
+
+    for expected in [
+        "out/config.json",
+        "out/0-99/index.json",
+        "results.csv",
+        "best.json",
+    ] {
+        assert!(listing.iter().any(|p| p == expected), "{:?}", listing);
+    }
+    assert!(
+        !listing.iter().any(|p| p.contains("/logs/")),
+        "{:?}",
+        listing
+    );
+
+    let files = archive_sweep(&cfg, &archive, true).unwrap();
+    let listing = archive_listing(&archive);
+
+    assert_eq!(listing.len(), files);
+    assert!(listing.iter().any(|p| p == "out/0-99/logs/log_6.txt"));
+    assert!(listing.iter().any(|p| p == "out/0-99/scripts/script_6.py"));
+}
+
+// what a combination's script and log take up in logs_dir
+fn combination_bytes(dir: &Path, i: usize) -> u64 {
+    [
+        format!("out/0-99/scripts/script_{}.py", i),
+        format!("out/0-99/logs/log_{}.txt", i),
+    ]
+    .iter()
+    .filter_map(|path| fs::metadata(dir.join(path)).ok())
+    .map(|metadata| metadata.len())
+    .sum()
+}
+
+#[test]
+fn output_budget_evicts_the_lowest_profits() {
+    let dir = scratch_dir("budget");
+
+    // room for about three combinations' outputs
+    let per_combination = {
+        let swept = sweep(&dir, &fake(), |_| {});
+        combination_bytes(&dir, swept.best().evaluation.index)
+    };
+    let limit = per_combination * 3;
+
+    let swept = sweep_over(&dir, 1, &fake(), |cfg| {
+        cfg.max_output_bytes = Some(limit);
+    });
+    assert_eq!(swept.best().evaluation.index, 6);
+    assert_eq!(swept.evaluations.len(), 8);
+
+    let kept = (0..8)
+        .filter(|i| dir.join(format!("out/0-99/logs/log_{}.txt", i)).exists())
+        .collect::<Vec<_>>();
+    // one at a time the lowest goes as each new one is written, leaving the top three
+    assert_eq!(kept, [5, 6, 7]);
+    assert!(
+        kept.iter()
+            .map(|i| combination_bytes(&dir, *i))
+            .sum::<u64>()
+            <= limit
+    );
+
+    let swept = sweep_over(&dir, 1, &fake(), |cfg| {
+        cfg.max_output_bytes = Some(limit);
+        cfg.on_output_budget = BudgetPolicy::Abort;
+    });
+    assert!(
+        swept.err().contains("grown past max_output_bytes"),
+        "{}",
+        swept.err()
+    );
+}
+
+#[test]
+fn results_csv_appends_tagged_sweeps() {
+    let dir = scratch_dir("results-csv");
+    let csv_path = dir.join("results.csv").to_str().unwrap().to_string();
+
+    for tag in ["first", "second"] {
+        sweep(&dir, &fake(), |cfg| {
+            cfg.results_csv = Some(csv_path.clone());
+            cfg.append_results = true;
+            cfg.run_tag = Some(tag.to_string());
+        })
+        .best();
+    }
+
+    let contents = fs::read_to_string(&csv_path).unwrap();
+    let mut lines = contents.lines();
+    assert_eq!(
+        lines.next(),
+        Some("tag,index,outcome,profit,duration_secs,x,y")
+    );
+
+    let rows: Vec<&str> = lines.collect();
+    assert_eq!(rows.len(), 16);
+    assert_eq!(rows.iter().filter(|r| r.starts_with("first,")).count(), 8);
+    assert_eq!(rows.iter().filter(|r| r.starts_with("second,")).count(), 8);
+    assert!(rows.iter().any(|r| r.starts_with("second,6,success,1500,")));
+
+    // another variable means other columns, so it can't go in the same file
+    let swept = sweep(&dir, &fake(), |cfg| {
+        cfg.variables.push(variable("z", 0., 1., 1.));
+        cfg.results_csv = Some(csv_path.clone());
+        cfg.append_results = true;
+    });
+    assert!(swept.err().contains("but this sweep writes"));
+    assert_eq!(fs::read_to_string(&csv_path).unwrap(), contents);
+}
+
+#[test]
+fn pre_command_runs_before_the_sweep() {
+    let dir = scratch_dir("pre-command");
+
+    let swept = sweep(&dir, &fake(), |cfg| {
+        cfg.pre_command = Some(PreCommand {
+            command: ["sh", "-c", "exit 3"].map(String::from).to_vec(),
+            dir: None,
+            env: BTreeMap::new(),
+        });
+    });
+    assert!(
+        swept.err().contains("pre_command sh failed"),
+        "{}",
+        swept.err()
+    );
+    assert!(!get_log_path(0, &swept.cfg).exists());
+
+    let swept = sweep(&dir, &fake(), |cfg| {
+        cfg.pre_command = Some(PreCommand {
+            command: ["sh", "-c", "echo $DATA > ready"]
+                .map(String::from)
+                .to_vec(),
+            dir: Some(dir.to_str().unwrap().to_string()),
+            env: BTreeMap::from([(String::from("DATA"), String::from("downloaded"))]),
+        });
+    });
+
+    assert_eq!(
+        fs::read_to_string(dir.join("ready")).unwrap(),
+        "downloaded\n"
+    );
+    assert_eq!(swept.best().profit, 1500.);
+    assert_eq!(swept.evaluations.len(), 8);
+}
+
+#[test]
+fn results_csv_takes_another_delimiter() {
+    let dir = scratch_dir("results-csv-delimiter");
+    let csv_path = dir.join("results.csv");
+
+    sweep(&dir, &fake(), |cfg| {
+        cfg.results_csv = Some(csv_path.to_str().unwrap().to_string());
+        cfg.run_tag = Some(String::from("eu; run"));
+        cfg.csv_delimiter = ';';
+        cfg.csv_quote = CsvQuote::NonNumeric;
+    })
+    .best();
+
+    let contents = fs::read_to_string(&csv_path).unwrap();
+    assert!(contents.starts_with("\"tag\";\"index\";\"outcome\";"));
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b';')
+        .from_reader(contents.as_bytes());
+    assert_eq!(
+        reader.headers().unwrap(),
+        vec![
+            "tag",
+            "index",
+            "outcome",
+            "profit",
+            "duration_secs",
+            "x",
+            "y"
+        ]
+    );
+
+    let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+    assert_eq!(rows.len(), 8);
+    assert!(rows.iter().all(|row| &row[0] == "eu; run"));
+    assert!(
+        rows.iter()
+            .any(|row| &row[1] == "6" && &row[3] == "1500" && &row[5] == "0.5")
+    );
+}
+
+#[test]
+fn results_csv_writes_the_configured_columns_in_order() {
+    let dir = scratch_dir("results-csv-columns");
+    let csv_path = dir.join("results.csv");
+
+    let mut cfg = sweep(&dir, &fake(), |cfg| {
+        cfg.results_csv = Some(csv_path.to_str().unwrap().to_string());
+        cfg.csv_columns = ["y", "profit", "x", "index"].map(String::from).to_vec();
+    })
+    .cfg;
+
+    let contents = fs::read_to_string(&csv_path).unwrap();
+    let mut lines = contents.lines();
+    assert_eq!(lines.next(), Some("y,profit,x,index"));
+    assert!(lines.any(|row| row == "2,1500,0.5,6"));
+
+    cfg.csv_columns.push(String::from("z"));
+    let err = validate_config(&cfg).unwrap_err();
+    assert!(err.to_string().contains("csv_columns has z"), "{}", err);
+}
+
+#[test]
+fn constants_hook_writes_a_dict_literal() {
+    let dir = scratch_dir("constants-hook");
+
+    let runner = ConstantsHook {
+        runner: fake(),
+        hook: |parameters: &BTreeMap<String, f64>| {
+            let entries = parameters
+                .iter()
+                .map(|(name, value)| format!("\"{}\": {}", name, value))
+                .collect::<Vec<_>>();
+
+            format!("PARAMS = {{{}}}", entries.join(", "))
+        },
+    };
+    sweep(&dir, &runner, |_| {}).best();
+
+    let script = fs::read_to_string(dir.join("out/0-99/scripts/script_6.py")).unwrap();
+    assert!(script.contains("# start\nPARAMS = {\"x\": 0.5, \"y\": 2}\n# end"));
+    assert!(!script.contains("x = "));
+}
+
+#[test]
+fn constants_command_writes_the_block() {
+    let dir = scratch_dir("constants-command");
+    let mut cfg = config(&dir);
+    cfg.constants_command = Some(
+        ["sh", "-c", "read parameters; echo \"PARAMS = $parameters\""]
+            .map(String::from)
+            .to_vec(),
+    );
+
+    let output = run_binary(&dir, &cfg, &[]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let script = fs::read_to_string(dir.join("out/0-99/scripts/script_6.py")).unwrap();
+    assert!(script.contains("# start\nPARAMS = {\"x\":0.5,\"y\":2.0}\n# end"));
+}
+
+#[test]
+fn bench_reports_throughput_per_thread_count() {
+    let dir = scratch_dir("bench");
+    let output = run_binary(
+        &dir,
+        &config(&dir),
+        &["bench", "--combos", "4", "--thread-counts", "1,2"],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Timing 4 runs of combination 0"));
+
+    for threads in ["1 threads: ", "2 threads: "] {
+        let line = stdout
+            .lines()
+            .map(str::trim_start)
+            .find(|l| l.starts_with(threads))
+            .unwrap();
+        let runs_per_sec: f64 = line[threads.len()..]
+            .split(' ')
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(runs_per_sec > 0.);
+        assert!(line.ends_with("s a run"));
+    }
+}
+
+// reports the round it was given as the profit
+const ROUND_BACKTESTER: &str = "#!/bin/sh\necho \"Total profit: $2\"\n";
+
+#[test]
+fn swept_round_is_passed_to_the_backtester() {
+    let dir = scratch_dir("swept-round");
+    let path = dir.join("round.sh");
+    fs::write(&path, ROUND_BACKTESTER).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let swept = sweep(&dir, &Backtester { path }, |cfg| {
+        cfg.variables[1] = variable("round", 1., 4., 1.);
+    });
+    assert_eq!(swept.evaluations.len(), 6);
+
+    for (i, evaluation) in &swept.evaluations {
+        assert_eq!(evaluation.profit, Some(evaluation.parameters["round"]));
+
+        let script =
+            fs::read_to_string(dir.join(format!("out/0-99/scripts/script_{}.py", i))).unwrap();
+        assert!(script.contains("x = "));
+        assert!(!script.contains("round = "));
+    }
+}
+
+#[test]
+fn no_variables_is_an_error_unless_allowed() {
+    let dir = scratch_dir("no-variables");
+    let mut cfg = config(&dir);
+    cfg.variables.clear();
+
+    let output = run_binary(&dir, &cfg, &[]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no variables to sweep"));
+    assert!(!dir.join("out/0-99/logs/log_0.txt").exists());
+
+    let output = run_binary(&dir, &cfg, &["--allow-empty"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("running the template once"));
+
+    // the fake backtester reads x and y as 0 from the untouched template
+    let manifest = read_manifest(&dir);
+    assert_eq!(manifest.len(), 1);
+    assert_eq!(manifest[&0].profit, Some(600.));
+}
+
+#[test]
+fn binary_prints_where_the_best_script_is() {
+    let dir = scratch_dir("best-paths");
+    let output = run_binary(&dir, &config(&dir), &[]);
+    assert!(output.status.success());
+
+    let state = load_state(dir.join("best.json").to_str().unwrap()).unwrap();
+    let index = state.best.unwrap().index;
+    let script_path = dir.join(format!("out/0-99/scripts/script_{}.py", index));
+    let log_path = dir.join(format!("out/0-99/logs/log_{}.txt", index));
+    assert_eq!(index, 6);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("script {}", script_path.display())));
+    assert!(stdout.contains(&format!("log {}", log_path.display())));
+
+    assert_eq!(
+        state.script_path.map(PathBuf::from),
+        Some(script_path.clone())
+    );
+    assert_eq!(state.log_path.map(PathBuf::from), Some(log_path));
+    assert!(
+        fs::read_to_string(script_path)
+            .unwrap()
+            .contains("x = 0.500\ny = 2")
+    );
+}
+
+#[cfg(feature = "msgpack")]
+#[test]
+fn binary_manifests_match_the_json_ones() {
+    let dir = scratch_dir("msgpack");
+    let cfg = sweep(&dir, &fake(), |cfg| cfg.binary_manifests = true).cfg;
+
+    let binary_path = dir.join("out/0-99/index.msgpack");
+    assert!(binary_path.exists());
+    let from_binary = load_manifests(&cfg.logs_dir).unwrap();
+
+    // without the binary file the json is read instead
+    fs::remove_file(&binary_path).unwrap();
+    let from_json = load_manifests(&cfg.logs_dir).unwrap();
+
+    assert_eq!(from_binary.len(), 8);
+    assert_eq!(
+        serde_json::to_value(&from_binary).unwrap(),
+        serde_json::to_value(&from_json).unwrap()
+    );
+}
+
+// the fake backtester, counting the runs made against round 3
+struct ValidationCounter {
+    inner: Backtester,
+    runs: Mutex<Vec<PathBuf>>,
+}
+
+impl Runner for ValidationCounter {
+    fn run(
+        &self,
+        i: usize,
+        parameters: &BTreeMap<String, f64>,
+        script_path: &Path,
+        stdin: Option<String>,
+        cfg: &Config,
+        in_flight: &InFlight,
+    ) -> Result<RunOutput, WorkerError> {
+        if cfg.round == 3 {
+            self.runs.lock().unwrap().push(script_path.to_path_buf());
+        }
+
+        self.inner
+            .run(i, parameters, script_path, stdin, cfg, in_flight)
+    }
+}
+
+#[test]
+fn validation_runs_the_best_once() {
+    let dir = scratch_dir("validation");
+
+    let runner = ValidationCounter {
+        inner: fake(),
+        runs: Mutex::new(Vec::new()),
+    };
+    let swept = sweep(&dir, &runner, |cfg| cfg.validation_round = Some(3));
+    let best = swept.best();
+
+    let runs = runner.runs.lock().unwrap();
+    assert_eq!(runs.len(), 1);
+
+    let script = fs::read_to_string(&runs[0]).unwrap();
+    assert!(script.contains(&best.constants));
+    assert_eq!(best.validation_profit, Some(1500.));
+}
+
+// a noisy backtester, its profit only depending on the seed it's given
+struct SeededRunner {
+    runs: Mutex<BTreeMap<usize, Vec<u64>>>,
+}
+
+impl Runner for SeededRunner {
+    fn run(
+        &self,
+        i: usize,
+        _parameters: &BTreeMap<String, f64>,
+        _script_path: &Path,
+        _stdin: Option<String>,
+        cfg: &Config,
+        _in_flight: &InFlight,
+    ) -> Result<RunOutput, WorkerError> {
+        let seed = cfg.seed.unwrap();
+        self.runs.lock().unwrap().entry(i).or_default().push(seed);
+
+        Ok(RunOutput {
+            stdout: format!("Total profit: {}\n", 100 * seed),
+            stderr: String::new(),
+            exit: Exit::Finished,
+        })
+    }
+}
+
+#[test]
+fn repeats_average_each_combination() {
+    let dir = scratch_dir("repeats");
+
+    let runner = SeededRunner {
+        runs: Mutex::new(BTreeMap::new()),
+    };
+    let swept = sweep(&dir, &runner, |cfg| cfg.repeats = 3);
+
+    let runs = runner.runs.lock().unwrap();
+    assert_eq!(runs.len(), 8);
+    assert!(runs.values().all(|seeds| seeds == &[0, 1, 2]));
+
+    // profits of 0, 100 and 200
+    assert!(swept.evaluations.values().all(|e| e.profit == Some(100.)));
+    assert!(
+        swept
+            .evaluations
+            .values()
+            .all(|e| e.profit_std == Some(100.))
+    );
+}
+
+// prints inf and nan for the first two combinations, as a strategy that blew up might
+struct BlowUpRunner;
+
+impl Runner for BlowUpRunner {
+    fn run(
+        &self,
+        i: usize,
+        _parameters: &BTreeMap<String, f64>,
+        _script_path: &Path,
+        _stdin: Option<String>,
+        _cfg: &Config,
+        _in_flight: &InFlight,
+    ) -> Result<RunOutput, WorkerError> {
+        let profit = match i {
+            0 => String::from("inf"),
+            1 => String::from("nan"),
+            _ => (100 * i).to_string(),
+        };
+
+        Ok(RunOutput {
+            stdout: format!("Total profit: {}\n", profit),
+            stderr: String::new(),
+            exit: Exit::Finished,
+        })
+    }
+}
+
+#[test]
+fn non_finite_profits_never_win() {
+    let dir = scratch_dir("non-finite");
+    let swept = sweep(&dir, &BlowUpRunner, |cfg| {
+        cfg.profit_pattern = String::from(r"Total profit:\s*(\S+)");
+    });
+
+    assert_eq!(swept.best().evaluation.index, 7);
+    assert_eq!(swept.best().profit, 700.);
+
+    for i in [0, 1] {
+        assert_eq!(swept.evaluations[&i].outcome, RunOutcome::NonFinite);
+        assert_eq!(swept.evaluations[&i].profit, None);
+    }
+}
+
+#[test]
+fn max_runs_stops_after_the_first_combinations() {
+    let dir = scratch_dir("max-runs");
+
+    let runner = SeededRunner {
+        runs: Mutex::new(BTreeMap::new()),
+    };
+    let swept = sweep(&dir, &runner, |cfg| {
+        cfg.max_runs = Some(3);
+        cfg.seed = Some(1);
+    });
+
+    let runs = runner.runs.lock().unwrap();
+    assert_eq!(runs.keys().copied().collect::<Vec<_>>(), [0, 1, 2]);
+    assert_eq!(swept.evaluations.len(), 3);
+}
+
+#[test]
+fn explain_reads_the_cached_run_time() {
+    let dir = scratch_dir("timing");
+    let mut cfg = config(&dir);
+    cfg.timing_cache = Some(dir.join("timing.json").to_str().unwrap().to_string());
+
+    let output = run_binary(&dir, &cfg, &[]);
+    assert!(output.status.success());
+
+    let timing: Timing =
+        serde_json::from_str(&fs::read_to_string(dir.join("timing.json")).unwrap()).unwrap();
+    assert_eq!(timing.runs, 8);
+
+    let output = run_binary(&dir, &cfg, &["--explain"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("averaged over 8 earlier runs"),
+        "{}",
+        stdout
+    );
+}
+
+// a git that reports the script as committed at abc123, with `status` as its
+// porcelain status
+fn stub_git(dir: &Path, status: &str) {
+    let bin_dir = dir.join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+
+    let git = bin_dir.join("git");
+    fs::write(
+        &git,
+        format!(
+            "#!/bin/sh\ncase \"$*\" in\n  *rev-parse*) echo abc123 ;;\n  *status*) printf '{}' ;;\nesac\n",
+            status
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&git, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[test]
+fn clean_git_is_recorded_with_the_results() {
+    let dir = scratch_dir("git-clean");
+    stub_git(&dir, "");
+
+    let output = run_binary(&dir, &config(&dir), &["--require-clean-git"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let copy: Config =
+        serde_json::from_str(&fs::read_to_string(dir.join("out/config.json")).unwrap()).unwrap();
+    assert_eq!(copy.script_commit.as_deref(), Some("abc123"));
+}
+
+#[test]
+fn dirty_git_aborts_before_running() {
+    let dir = scratch_dir("git-dirty");
+    stub_git(&dir, " M trader.py");
+
+    let output = run_binary(&dir, &config(&dir), &["--require-clean-git"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("changes that aren't committed"));
+    assert!(!dir.join("out").exists());
+
+    // without the flag it runs, recording that the script was dirty
+    let output = run_binary(&dir, &config(&dir), &[]);
+    assert!(output.status.success());
+
+    let copy: Config =
+        serde_json::from_str(&fs::read_to_string(dir.join("out/config.json")).unwrap()).unwrap();
+    assert_eq!(copy.script_commit.as_deref(), Some("abc123-dirty"));
+}
+
+// flushes a profit when asked to stop with x = 0, ignores the request
+// otherwise, and never finishes on its own
+// the fake backtester, except x = 0.5 and y = 3, combination 7, hangs
+const HANGING_BACKTESTER: &str = r#"#!/bin/sh
+if grep -q "^x = 0\.5" "$1" && grep -q "^y = 3$" "$1"; then
+    exec sleep 30
+fi
+exec "$(dirname "$0")/fake.sh" "$1"
+"#;
+
+#[test]
+fn skip_file_skips_the_run_in_flight() {
+    let dir = scratch_dir("skip");
+    let bin_dir = dir.join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    symlink(fake_backtester(), bin_dir.join("fake.sh")).unwrap();
+    fs::write(bin_dir.join("prosperity3bt"), HANGING_BACKTESTER).unwrap();
+    fs::set_permissions(
+        bin_dir.join("prosperity3bt"),
+        fs::Permissions::from_mode(0o755),
+    )
+    .unwrap();
+
+    let cfg = config(&dir);
+    let cfg_path = dir.join("config.json");
+    fs::write(&cfg_path, serde_json::to_string(&cfg).unwrap()).unwrap();
+
+    let started = Instant::now();
+    let child = Command::new(env!("CARGO_BIN_EXE_grid_search"))
+        .arg("--config")
+        .arg(&cfg_path)
+        .args(["--threads", "8"])
+        .env("PATH", format!("{}:/usr/bin:/bin", bin_dir.display()))
+        .env_remove("PROSPERITY3BT")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // once the quick combinations are done only the hanging one is left to skip
+    while get_log_path(6, &cfg)
+        .parent()
+        .is_none_or(|logs| !logs.exists())
+    {
+        thread::sleep(Duration::from_millis(50));
+    }
+    thread::sleep(Duration::from_millis(500));
+    fs::write(skip_file_path(&cfg), "").unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert!(started.elapsed() < Duration::from_secs(20));
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 combinations were skipped"));
+
+    let manifest = read_manifest(&dir);
+    assert_eq!(manifest[&7].outcome, RunOutcome::Skipped);
+    assert!(
+        (0..7).all(|i| manifest[&i].outcome == RunOutcome::Success),
+        "{:?}",
+        manifest
+    );
+    assert!(!skip_file_path(&cfg).exists());
+}
+
+// only odd combinations print a profit, and all of them a loss, so a
+// missing profit counted as 0 wins
+struct GappyRunner;
+
+impl Runner for GappyRunner {
+    fn run(
+        &self,
+        i: usize,
+        _parameters: &BTreeMap<String, f64>,
+        _script_path: &Path,
+        _stdin: Option<String>,
+        _cfg: &Config,
+        _in_flight: &InFlight,
+    ) -> Result<RunOutput, WorkerError> {
+        let stdout = match i % 2 {
+            1 => format!("Total profit: -{}\n", 100 * i),
+            _ => String::from("Backtest finished\n"),
+        };
+
+        Ok(RunOutput {
+            stdout,
+            stderr: String::new(),
+            exit: Exit::Finished,
+        })
+    }
+}
+
+#[test]
+fn parse_failures_count_as_zero_skip_or_abort() {
+    let dir = scratch_dir("parse-failure");
+
+    let zero = sweep(&dir, &GappyRunner, |cfg| {
+        cfg.profit_pattern = String::from(r"Total profit: (-\d+)");
+        cfg.on_parse_failure = ParseFailure::Zero;
+    });
+    assert_eq!(zero.best().profit, 0.);
+    assert_eq!(zero.best().evaluation.index % 2, 0);
+    assert!(
+        (0..8)
+            .step_by(2)
+            .all(|i| zero.evaluations[&i].profit == Some(0.))
+    );
+
+    let skip = sweep(&dir, &GappyRunner, |cfg| {
+        cfg.profit_pattern = String::from(r"Total profit: (-\d+)");
+        cfg.on_parse_failure = ParseFailure::Skip;
+    });
+    assert_eq!(skip.best().profit, -100.);
+    assert_eq!(skip.best().evaluation.index, 1);
+    for i in (0..8).step_by(2) {
+        assert_eq!(skip.evaluations[&i].outcome, RunOutcome::NoProfit);
+        assert_eq!(skip.evaluations[&i].profit, None);
+    }
+
+    let abort = sweep_over(&dir, 1, &GappyRunner, |cfg| {
+        cfg.profit_pattern = String::from(r"Total profit: (-\d+)");
+        cfg.on_parse_failure = ParseFailure::Abort;
+    });
+    assert!(
+        abort.err().contains("combination 0 produced no profit"),
+        "{}",
+        abort.err()
+    );
+}
+
+// a long-lived pool worker, logging each start to $1 and running the fake
+// backtester, $2, on the script of every request it's sent
+const POOL_WORKER: &str = r#"#!/bin/sh
+echo $$ >> "$1"
+while read -r request; do
+    script=$(echo "$request" | sed 's/.*"script":"\([^"]*\)".*/\1/')
+    "$2" "$script"
+    echo "grid_search:done"
+done
+"#;
+
+#[test]
+fn pool_workers_run_many_combinations_each() {
+    let dir = scratch_dir("pool");
+    let path = dir.join("worker.sh");
+    fs::write(&path, POOL_WORKER).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    let starts = dir.join("starts.txt");
+
+    let swept = sweep_over(&dir, 2, &Backtester { path }, |cfg| {
+        cfg.worker_mode = WorkerMode::Pool;
+        cfg.worker_args = vec![
+            starts.to_str().unwrap().to_string(),
+            fake_backtester().to_str().unwrap().to_string(),
+        ];
+    });
+
+    assert_eq!(swept.best().profit, 1500.);
+    assert_eq!(swept.best().evaluation.index, 6);
+    assert_eq!(swept.evaluations.len(), 8);
+    assert!(
+        swept
+            .evaluations
+            .values()
+            .all(|e| e.outcome == RunOutcome::Success)
+    );
+
+    // no more than one worker a thread for all 8
+    let started = fs::read_to_string(&starts).unwrap().lines().count();
+    assert!((1..=2).contains(&started), "{} workers", started);
+}
+
+const SLOW_BACKTESTER: &str = r#"#!/bin/sh
+if grep -q "^x = 0$" "$1"; then
+    trap 'echo "Total profit: 7"; exit 0' TERM
+else
+    trap '' TERM
+fi
+sleep 10 >/dev/null 2>&1 &
+wait
+"#;
+
+#[test]
+fn soft_timeout_lets_a_cooperative_backtester_flush() {
+    let dir = scratch_dir("soft-timeout");
+    let path = dir.join("slow.sh");
+    fs::write(&path, SLOW_BACKTESTER).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let swept = sweep_over(&dir, 8, &Backtester { path }, |cfg| {
+        cfg.soft_timeout_secs = Some(1);
+        cfg.hard_timeout_secs = Some(2);
+        cfg.continue_on_error = true;
+    });
+
+    for (i, evaluation) in &swept.evaluations {
+        let log = fs::read_to_string(dir.join(format!("out/0-99/logs/log_{}.txt", i))).unwrap();
+
+        // what was flushed on SIGTERM is scored, the rest are killed without one
+        if evaluation.parameters["x"] == 0. {
+            assert_eq!(evaluation.outcome, RunOutcome::SoftTimeout);
+            assert_eq!(evaluation.profit, Some(7.));
+            assert!(log.contains("stopped at the 1s soft timeout"));
+            assert!(log.contains("Total profit: 7"));
+        } else {
+            assert_eq!(evaluation.outcome, RunOutcome::Timeout);
+            assert_eq!(evaluation.profit, None);
+            assert!(log.contains("timed out after 2s"));
+        }
+    }
+    assert_eq!(swept.best().profit, 7.);
+
+    // and read back the same from the logs alone
+    let reloaded = load_evaluations(&swept.cfg.logs_dir, &swept.cfg).unwrap();
+    assert_eq!(reloaded[0].outcome, RunOutcome::SoftTimeout);
+    assert_eq!(reloaded[0].profit, Some(7.));
+}
+
+#[test]
+fn progress_is_plain_when_stderr_is_not_a_terminal() {
+    let dir = scratch_dir("progress");
+
+    // stderr is a pipe here, so auto shouldn't redraw anything
+    let output = run_binary(&dir, &config(&dir), &[]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("8/8 combinations (100%)"));
+    assert!(!stderr.contains('\r') && !stderr.contains('\x1b'));
+
+    let output = run_binary(&dir, &config(&dir), &["--progress", "always"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("\r8/8 combinations"));
+
+    let output = run_binary(&dir, &config(&dir), &["--progress", "never"]);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("combinations"));
+}
+
+#[test]
+fn resume_carries_on_from_the_cursor() {
+    let dir = scratch_dir("resume");
+
+    let first = SeededRunner {
+        runs: Mutex::new(BTreeMap::new()),
+    };
+    let mut cfg = sweep(&dir, &first, |cfg| {
+        cfg.seed = Some(7);
+        cfg.max_runs = Some(3);
+    })
+    .cfg;
+    assert_eq!(load_cursor(&cfg).unwrap().next, 3);
+
+    // the cursor is written as if the run stopped partway through
+    cfg.max_runs = None;
+    save_cursor(&cfg, 2).unwrap();
+
+    let second = SeededRunner {
+        runs: Mutex::new(BTreeMap::new()),
+    };
+    let swept = sweep(&dir, &second, |cfg| {
+        cfg.seed = Some(7);
+        cfg.resume = true;
+    });
+
+    // nothing but the cursor decides what runs, so 2 runs again even though
+    // it had finished
+    let ran = second.runs.into_inner().unwrap();
+    assert_eq!(
+        ran.keys().copied().collect::<Vec<_>>(),
+        vec![2, 3, 4, 5, 6, 7]
+    );
+    assert_eq!(swept.evaluations.len(), 8);
+    assert_eq!(load_cursor(&swept.cfg).unwrap().next, 8);
+
+    // a different sweep can't pick up this one's cursor
+    let swept = sweep(&dir, &first, |cfg| {
+        cfg.seed = Some(7);
+        cfg.resume = true;
+        cfg.round = 3;
+    });
+    assert!(swept.err().contains("the config has changed"));
+
+    // a grid far too big to generate from the start, so only what's past
+    // the cursor can have been
+    let dir = scratch_dir("resume-huge");
+    let huge = |cfg: &mut Config| {
+        cfg.seed = Some(7);
+        cfg.variables = vec![variable("x", 0., 1e6, 1.), variable("y", 0., 1e6, 1.)];
+    };
+    let mut cfg = config(&dir);
+    huge(&mut cfg);
+    let total = count_combinations(&cfg) as usize;
+    fs::create_dir_all(&cfg.logs_dir).unwrap();
+    save_cursor(&cfg, total - 3).unwrap();
+
+    let third = SeededRunner {
+        runs: Mutex::new(BTreeMap::new()),
+    };
+    let swept = sweep(&dir, &third, |cfg| {
+        huge(cfg);
+        cfg.resume = true;
+    });
+
+    let ran = third.runs.into_inner().unwrap();
+    assert_eq!(
+        ran.keys().copied().collect::<Vec<_>>(),
+        vec![total - 3, total - 2, total - 1]
+    );
+    assert_eq!(swept.best().constants, "x = 999999\ny = 999997");
+    assert_eq!(load_cursor(&swept.cfg).unwrap().next, total);
+}
+
+// writes its profit to results_<i>.json rather than stdout, which only has a
+// profit that would lose
+struct ResultsJsonRunner {
+    dir: PathBuf,
+}
+
+impl Runner for ResultsJsonRunner {
+    fn run(
+        &self,
+        i: usize,
+        _parameters: &BTreeMap<String, f64>,
+        _script_path: &Path,
+        _stdin: Option<String>,
+        _cfg: &Config,
+        _in_flight: &InFlight,
+    ) -> Result<RunOutput, WorkerError> {
+        let results = serde_json::json!({ "summary": { "pnl": 100 * i } });
+        fs::write(
+            self.dir.join(format!("results_{}.json", i)),
+            results.to_string(),
+        )
+        .unwrap();
+
+        Ok(RunOutput {
+            stdout: String::from("Total profit: 1\n"),
+            stderr: String::new(),
+            exit: Exit::Finished,
+        })
+    }
+}
+
+#[test]
+fn profit_is_read_from_the_results_json() {
+    let dir = scratch_dir("results-json");
+
+    let runner = ResultsJsonRunner {
+        dir: dir.to_path_buf(),
+    };
+    let swept = sweep(&dir, &runner, |cfg| {
+        cfg.results_json = Some(ResultsJson {
+            path: dir
+                .join("results_{index}.json")
+                .to_str()
+                .unwrap()
+                .to_string(),
+            profit: String::from("/summary/pnl"),
+        });
+    });
+
+    assert_eq!(swept.best().evaluation.index, 7);
+    assert_eq!(swept.best().profit, 700.);
+}
+
+#[test]
+fn labels_show_in_the_summary_but_not_the_script() {
+    let dir = scratch_dir("labels");
+    let mut cfg = config(&dir);
+    cfg.variables[0].label = Some(String::from("Take Width"));
+    cfg.variables[0].unit = Some(String::from("ticks"));
+
+    let output = run_binary(&dir, &cfg, &[]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("  Take Width (ticks) = 0.5 (tried 2 values"),
+        "{}",
+        stdout
+    );
+
+    let script = fs::read_to_string(dir.join("out/0-99/scripts/script_0.py")).unwrap();
+    assert!(script.contains("# start\nx = 0\n"));
+}
+
+// the fake backtester, with one combination taking its time
+struct SlowOn {
+    slow: usize,
+}
+
+impl Runner for SlowOn {
+    fn run(
+        &self,
+        i: usize,
+        parameters: &BTreeMap<String, f64>,
+        script_path: &Path,
+        stdin: Option<String>,
+        cfg: &Config,
+        in_flight: &InFlight,
+    ) -> Result<RunOutput, WorkerError> {
+        if i == self.slow {
+            thread::sleep(Duration::from_secs(1));
+        }
+
+        fake().run(i, parameters, script_path, stdin, cfg, in_flight)
+    }
+}
+
+#[test]
+fn duration_penalty_changes_the_winner() {
+    let dir = scratch_dir("duration-objective");
+    let runner = SlowOn { slow: 6 };
+
+    assert_eq!(sweep(&dir, &runner, |_| {}).best().evaluation.index, 6);
+
+    // 1500 less at least 300 for the slow one loses to 1400 run quickly
+    let swept = sweep(&dir, &runner, |cfg| {
+        cfg.objective = Some(String::from("profit - 300 * duration_secs"));
+    });
+    let best = swept.best();
+
+    assert!([5, 7].contains(&best.evaluation.index), "{:?}", best);
+    assert_eq!(best.evaluation.raw_profit, Some(1400.));
+    assert!(
+        best.profit < 1400. && best.profit > 1300.,
+        "{}",
+        best.profit
+    );
+
+    let slow = &swept.evaluations[&6];
+    assert_eq!(slow.raw_profit, Some(1500.));
+    assert!(slow.profit.unwrap() <= 1200., "{:?}", slow);
+}
+
+// the fake backtester, keeping the parameters the baseline was run with
+struct BaselineRecorder {
+    parameters: Mutex<Option<BTreeMap<String, f64>>>,
+}
+
+impl Runner for BaselineRecorder {
+    fn run(
+        &self,
+        i: usize,
+        parameters: &BTreeMap<String, f64>,
+        script_path: &Path,
+        stdin: Option<String>,
+        cfg: &Config,
+        in_flight: &InFlight,
+    ) -> Result<RunOutput, WorkerError> {
+        if i == usize::MAX {
+            *self.parameters.lock().unwrap() = Some(parameters.clone());
+        }
+
+        fake().run(i, parameters, script_path, stdin, cfg, in_flight)
+    }
+}
+
+#[test]
+fn baseline_delta_is_profit_less_the_baseline() {
+    let dir = scratch_dir("baseline");
+    // a constant outside the block isn't one the baseline ran with
+    fs::write(dir.join("trader.py"), format!("window = 20\n{}", TEMPLATE)).unwrap();
+
+    let runner = BaselineRecorder {
+        parameters: Mutex::new(None),
+    };
+    let swept = sweep(&dir, &runner, |cfg| cfg.baseline = true);
+
+    // the template as it is has x = 0 and y = 0, making 600
+    assert_eq!(
+        runner.parameters.lock().unwrap().take(),
+        Some(BTreeMap::from([
+            (String::from("x"), 0.),
+            (String::from("y"), 0.)
+        ]))
+    );
+    assert_eq!(swept.evaluations.len(), 8);
+    for evaluation in swept.evaluations.values() {
+        assert_eq!(
+            evaluation.baseline_delta,
+            Some(evaluation.profit.unwrap() - 600.)
+        );
+    }
+
+    let swept = sweep(&dir, &runner, |cfg| {
+        cfg.baseline = true;
+        cfg.baseline_parameters = Some(BTreeMap::from([
+            (String::from("x"), 0.5),
+            (String::from("y"), 1.),
+        ]));
+    });
+    assert_eq!(swept.best().evaluation.baseline_delta, Some(100.));
+    assert_eq!(swept.evaluations[&0].baseline_delta, Some(-800.));
+
+    let baseline = fs::read_to_string(dir.join("out/baseline.py")).unwrap();
+    assert!(
+        baseline.contains("# start\nx = 0.500\ny = 1\n# end"),
+        "{}",
+        baseline
+    );
+}
+
+#[test]
+fn reaching_the_target_stops_the_sweep() {
+    let dir = scratch_dir("target");
+    // combination 5, x = 0.5 and y = 1, is the first to make 1400
+    let swept = sweep_over(&dir, 1, &fake(), |cfg| cfg.target_objective = Some(1400.));
+
+    assert_eq!(swept.best().evaluation.index, 5);
+    assert_eq!(swept.best().profit, 1400.);
+    assert_eq!(swept.evaluations.len(), 6);
+
+    // with every combination in flight at once, the slow 1500 finishes after
+    // 5 or 7 has made 1400, and doesn't take the best from it
+    let swept = sweep_over(&dir, 8, &SlowOn { slow: 6 }, |cfg| {
+        cfg.target_objective = Some(1400.)
+    });
+    let best = swept.best();
+
+    assert!([5, 7].contains(&best.evaluation.index), "{:?}", best);
+    assert_eq!(best.profit, 1400.);
+    assert_eq!(swept.evaluations[&6].profit, Some(1500.));
+
+    let state = load_state(swept.cfg.state_file.as_deref().unwrap()).unwrap();
+    assert_eq!(state.max_profit, 1400.);
+    assert_eq!(state.best.unwrap().index, best.evaluation.index);
+}
+
+#[test]
+fn backtester_sees_the_combination_in_its_environment() {
+    let dir = scratch_dir("env");
+
+    let backtester = dir.join("env_backtester.sh");
+    fs::write(
+        &backtester,
+        "#!/bin/sh\necho \"Total profit: 1\"\necho \"index=$GRID_SEARCH_INDEX\"\necho \"params=$GRID_SEARCH_PARAMS\"\n",
+    )
+    .unwrap();
+    fs::set_permissions(&backtester, fs::Permissions::from_mode(0o755)).unwrap();
+
+    sweep(&dir, &Backtester { path: backtester }, |_| {}).best();
+
+    let log = fs::read_to_string(dir.join("out/0-99/logs/log_6.txt")).unwrap();
+    assert!(log.contains("index=6\n"), "{}", log);
+    assert!(log.contains(r#"params={"x":0.5,"y":2.0}"#), "{}", log);
+}
+
+#[test]
+fn blocklisted_combinations_never_run() {
+    let dir = scratch_dir("blocklist");
+
+    let runner = SeededRunner {
+        runs: Mutex::new(BTreeMap::new()),
+    };
+    sweep(&dir, &runner, |cfg| {
+        cfg.seed = Some(1);
+        cfg.blocklist = vec![
+            BTreeMap::from([(String::from("x"), 0.5), (String::from("y"), 2.)]),
+            // every combination with y = 0
+            BTreeMap::from([(String::from("y"), 0.)]),
+        ];
+    })
+    .best();
+
+    let runs = runner.runs.lock().unwrap();
+    assert_eq!(runs.keys().copied().collect::<Vec<_>>(), [1, 2, 3, 5, 7]);
+}
+
+#[test]
+fn slices_differing_only_in_how_they_ran_merge_quietly() {
+    let dir = scratch_dir("merge-slices");
+    let cfg = config(&dir);
+
+    let first = run_binary(&dir, &cfg, &["--shard", "0/2", "--note", "first half"]);
+    assert!(first.status.success());
+    let second = run_binary(
+        &dir,
+        &cfg,
+        &["--shard", "1/2", "--max-runs", "3", "--continue-on-error"],
+    );
+    assert!(second.status.success());
+
+    let slices = [dir.join("out/shard-0-of-2"), dir.join("out/shard-1-of-2")];
+    let merge = |dir: &Path| {
+        let mut args = vec!["merge", "--inputs"];
+        args.extend(slices.iter().map(|slice| slice.to_str().unwrap()));
+        run_binary(dir, &cfg, &args)
+    };
+
+    let output = merge(&dir);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("different sweep"), "{}", stderr);
+
+    // a slice that swept other values is still called out
+    let copy = slices[1].join("config.json");
+    let mut slice_cfg: Config = serde_json::from_str(&fs::read_to_string(&copy).unwrap()).unwrap();
+    slice_cfg.variables[1] = variable("y", 0., 8., 2.);
+    fs::write(&copy, serde_json::to_string(&slice_cfg).unwrap()).unwrap();
+
+    let output = merge(&dir);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("was run with a different sweep"),
+        "{}",
+        stderr
+    );
+    assert!(!stderr.contains("note"), "{}", stderr);
+
+    // as is one whose indices map to other points, here by nesting y outside x
+    slice_cfg.variables[1] = variable("y", 0., 4., 1.);
+    slice_cfg.order = vec![String::from("y"), String::from("x")];
+    fs::write(&copy, serde_json::to_string(&slice_cfg).unwrap()).unwrap();
+
+    let output = merge(&dir);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("different sweep"), "{}", stderr);
+    assert!(stderr.contains("order"), "{}", stderr);
+}
@@ -0,0 +1,8 @@
+#!/bin/sh
+# stands in for prosperity3bt, printing a profit computed from the constants
+# in the script it's given: 1000 + 1000 * x - 100 * (y - 2)^2
+awk -F' = ' '
+    $1 == "x" { x = $2 }
+    $1 == "y" { y = $2 }
+    END { printf "Total profit: %d\n", 1000 + 1000 * x - 100 * (y - 2) ^ 2 }
+' "$1"
@@ -0,0 +1,194 @@
+use grid_search::{
+    combinations_from,
+    config::{ActiveWhen, ConfigBuilder, validate_config},
+    count_combinations,
+    float_range::FloatRange,
+    get_constant_strings, values_for,
+};
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+fn values(start: f64, end: f64, step: f64) -> Vec<f64> {
+    FloatRange::new(start, end, step).collect()
+}
+
+fn assert_close(actual: &[f64], expected: &[f64]) {
+    assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < 1e-9, "{:?}", actual);
+    }
+}
+
+#[test]
+fn end_a_whole_number_of_steps_away_is_excluded() {
+    // (0.4 - 0.1) / 0.1 is 3.0000000000000004
+    assert_close(&values(0.1, 0.4, 0.1), &[0.1, 0.2, 0.3]);
+    assert_close(&values(0.7, 1.0, 0.1), &[0.7, 0.8, 0.9]);
+
+    // (0.9 - 0.3) / 0.3 is 2.0000000000000004
+    assert_close(&values(0.3, 0.9, 0.3), &[0.3, 0.6]);
+    assert_close(&values(0.2, 1.1, 0.3), &[0.2, 0.5, 0.8]);
+
+    // and just below, (0.3 - 0) / 0.1 is 2.9999999999999996
+    assert_close(&values(0., 0.3, 0.1), &[0., 0.1, 0.2]);
+}
+
+#[test]
+fn end_part_way_through_a_step_is_still_reached() {
+    assert_close(&values(0., 0.35, 0.1), &[0., 0.1, 0.2, 0.3]);
+    assert_close(&values(0., 1., 0.3), &[0., 0.3, 0.6, 0.9]);
+}
+
+#[test]
+fn forced_endpoints_add_an_end_the_steps_miss() {
+    let mut cfg = ConfigBuilder::new()
+        .add_variable("edge", 0., 1., 0.3)
+        .add_variable("width", 0., 1., 0.25)
+        .build()
+        .unwrap();
+    for var in &mut cfg.variables {
+        var.force_endpoints = true;
+    }
+
+    assert_close(&values_for(&cfg.variables[0]), &[0., 0.3, 0.6, 0.9, 1.]);
+    // landing on end exactly doesn't run it twice
+    assert_close(&values_for(&cfg.variables[1]), &[0., 0.25, 0.5, 0.75, 1.]);
+
+    let forced = FloatRange::with_end(0., 1., 0.3);
+    assert_eq!(forced.value_count(), 5);
+    assert_eq!(forced.value_at(4), 1.);
+}
+
+#[test]
+fn forced_endpoints_need_a_step_towards_end() {
+    let mut cfg = ConfigBuilder::new()
+        .add_variable("edge", 1., 0., 0.3)
+        .build()
+        .unwrap();
+    cfg.variables[0].force_endpoints = true;
+
+    // it would otherwise run 0 alone, without the start
+    let err = validate_config(&cfg).unwrap_err();
+    assert!(
+        err.to_string().contains("never gets from 1 to 0"),
+        "{}",
+        err
+    );
+
+    cfg.variables[0].step = Some(-0.3);
+    validate_config(&cfg).unwrap();
+    assert_close(&values_for(&cfg.variables[0]), &[1., 0.7, 0.4, 0.1, 0.]);
+
+    // a single value is both ends at once
+    cfg.variables[0].end = 1.;
+    validate_config(&cfg).unwrap();
+    assert_close(&values_for(&cfg.variables[0]), &[1.]);
+}
+
+#[test]
+fn count_spreads_the_values_evenly_over_both_ends() {
+    let range = FloatRange::with_count(0., 1., 5);
+
+    assert_eq!(range.len(), 5);
+    assert_close(&range.collect::<Vec<_>>(), &[0., 0.25, 0.5, 0.75, 1.]);
+
+    assert_close(
+        &FloatRange::with_count(2., 3., 1).collect::<Vec<_>>(),
+        &[2.],
+    );
+    assert_eq!(FloatRange::with_count(0., 1., 0).count(), 0);
+}
+
+#[test]
+fn random_ranges_never_overshoot_end() {
+    let mut rng = StdRng::seed_from_u64(400);
+
+    for _ in 0..5000 {
+        let start = rng.random_range(-100.0..100.0);
+        let end = start + rng.random_range(0.0..50.0);
+        // steps from a sliver up to past the whole span
+        let step = rng.random_range(0.001..60.0);
+
+        let values = values(start, end, step);
+
+        if let Some(last) = values.last() {
+            assert!(
+                *last <= end + step * 1e-9,
+                "{} {} {} ended at {}",
+                start,
+                end,
+                step,
+                last
+            );
+        }
+    }
+}
+
+// step and count variables, forced endpoints, active_when and a custom order
+// mixed at random, the count has to match what's generated from any index
+#[test]
+fn random_configs_generate_what_they_count() {
+    let mut rng = StdRng::seed_from_u64(400);
+
+    for _ in 0..200 {
+        let mut builder = ConfigBuilder::new();
+        for v in 0..rng.random_range(1..=4) {
+            let name = format!("v{}", v);
+            let start = rng.random_range(-10.0..10.0);
+            let end = start + rng.random_range(0.0..3.0);
+
+            builder = match rng.random_bool(0.3) {
+                true => builder.add_variable_count(&name, start, end, rng.random_range(1..5)),
+                false => builder.add_variable(&name, start, end, rng.random_range(0.5..2.0)),
+            };
+        }
+        let mut cfg = builder.build().unwrap();
+
+        for var in cfg.variables.iter_mut().filter(|var| var.step.is_some()) {
+            var.force_endpoints = rng.random_bool(0.5);
+        }
+
+        // only on v0, which is then always nested outside them
+        let v0_values = values_for(&cfg.variables[0]);
+        for var in cfg.variables.iter_mut().skip(1) {
+            if rng.random_bool(0.4) {
+                // always at least the first, an empty list isn't valid
+                let values = v0_values
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j == 0 || rng.random_bool(0.5))
+                    .map(|(_, value)| *value)
+                    .collect::<Vec<_>>();
+                var.active_when = Some(ActiveWhen {
+                    variable: String::from("v0"),
+                    values,
+                });
+            }
+        }
+
+        if rng.random_bool(0.5) {
+            let mut inner = cfg.variables[1..]
+                .iter()
+                .map(|var| var.name.clone())
+                .collect::<Vec<_>>();
+            inner.shuffle(&mut rng);
+            cfg.order = [vec![String::from("v0")], inner].concat();
+        }
+
+        validate_config(&cfg).unwrap();
+
+        let all = get_constant_strings(&cfg);
+        assert_eq!(count_combinations(&cfg), all.len() as u128, "{:?}", cfg);
+
+        for k in [0, 1, all.len() / 2, all.len().saturating_sub(1), all.len()] {
+            let k = k.min(all.len());
+            assert_eq!(
+                combinations_from(&cfg, k).collect::<Vec<_>>(),
+                all[k..],
+                "{:?} from {}",
+                cfg,
+                k
+            );
+        }
+    }
+}
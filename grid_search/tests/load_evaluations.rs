@@ -0,0 +1,71 @@
+use std::{collections::BTreeMap, fs};
+
+use grid_search::{
+    config::ConfigBuilder,
+    create_or_clean_logs_dir,
+    evaluation::{Evaluation, RunOutcome},
+    get_log_path,
+    manifest::{load_evaluations, write_manifests},
+};
+
+#[test]
+fn logs_load_back_into_evaluations() {
+    let logs_dir = std::env::temp_dir().join(format!("grid_search-load-{}", std::process::id()));
+    let mut cfg = ConfigBuilder::new()
+        .logs_dir(logs_dir.to_str().unwrap())
+        .add_variable("x", 0., 250., 50.)
+        .build()
+        .unwrap();
+    cfg.bucket_size = 2;
+
+    create_or_clean_logs_dir(&cfg, 5);
+
+    // the first bucket finished and wrote its manifest, which wins over its logs
+    let finished = [0, 1]
+        .map(|index| Evaluation {
+            index,
+            parameters: BTreeMap::from([(String::from("x"), index as f64 * 50.)]),
+            profit: Some(100. * index as f64),
+            outcome: RunOutcome::Success,
+            raw_profit: None,
+            baseline_delta: None,
+            profit_std: None,
+            duration_secs: 2.,
+            error: None,
+        })
+        .to_vec();
+    write_manifests(&finished, &cfg).unwrap();
+
+    let logs = [
+        (1, "Stdout:\n\n\n\nStderr:\n\n\n\nProfit: 999"),
+        (2, "Stdout:\nProfit: 1\n\n\nStderr:\n\n\n\nProfit: 1500"),
+        (
+            3,
+            "No profit found. Tail of stderr:\nTraceback\n\n\nStdout:\n\n\n\nStderr:\nTraceback\n\n\nNo profit found.",
+        ),
+        // cut off part way through writing
+        (4, "Stdout:\nrunning day"),
+    ];
+    for (index, log) in logs {
+        fs::write(get_log_path(index, &cfg), log).unwrap();
+    }
+
+    let evaluations = load_evaluations(logs_dir.to_str().unwrap(), &cfg).unwrap();
+
+    let indices = evaluations.iter().map(|e| e.index).collect::<Vec<_>>();
+    assert_eq!(indices, [0, 1, 2, 3]);
+
+    assert_eq!(evaluations[1].profit, Some(100.));
+    assert_eq!(evaluations[1].duration_secs, 2.);
+
+    assert_eq!(evaluations[2].profit, Some(1500.));
+    assert_eq!(evaluations[2].outcome, RunOutcome::Success);
+    assert_eq!(evaluations[2].parameters["x"], 100.);
+
+    // on_parse_failure defaults to zero
+    assert_eq!(evaluations[3].profit, Some(0.));
+    assert_eq!(evaluations[3].outcome, RunOutcome::NoProfit);
+    assert_eq!(evaluations[3].parameters["x"], 150.);
+
+    fs::remove_dir_all(&logs_dir).unwrap();
+}
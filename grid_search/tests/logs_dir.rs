@@ -0,0 +1,27 @@
+use std::fs;
+
+use grid_search::{config::ConfigBuilder, create_or_clean_logs_dir, get_bucket_dir};
+
+#[test]
+fn cleaning_tolerates_a_missing_bucket() {
+    let logs_dir = std::env::temp_dir().join(format!("grid_search-clean-{}", std::process::id()));
+    let cfg = ConfigBuilder::new()
+        .logs_dir(logs_dir.to_str().unwrap())
+        .add_variable("x", 0., 250., 1.)
+        .build()
+        .unwrap();
+
+    create_or_clean_logs_dir(&cfg, 250);
+    fs::remove_dir_all(get_bucket_dir(100, &cfg)).unwrap();
+
+    create_or_clean_logs_dir(&cfg, 250);
+
+    // every combination, the final partial bucket's included, has somewhere to go
+    for i in [0, 99, 100, 199, 200, 249] {
+        assert!(get_bucket_dir(i, &cfg).join("scripts").is_dir());
+        assert!(get_bucket_dir(i, &cfg).join("logs").is_dir());
+    }
+    assert!(!get_bucket_dir(300, &cfg).exists());
+
+    fs::remove_dir_all(&logs_dir).unwrap();
+}
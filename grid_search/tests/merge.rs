@@ -0,0 +1,80 @@
+use std::{fs, path::Path};
+
+use grid_search::{config::ConfigBuilder, run::merge_slices, state::load_state};
+
+fn fixture(name: &str) -> String {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[test]
+fn merged_shards_keep_the_best_of_both() {
+    let dir = std::env::temp_dir().join(format!("grid_search-merge-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let combined = dir.join("combined.csv");
+    let state_file = dir.join("best.json");
+
+    let mut cfg = ConfigBuilder::new()
+        .state_file(state_file.to_str().unwrap())
+        .add_variable("x", 0., 1., 0.5)
+        .add_variable("y", 0., 4., 1.)
+        .build()
+        .unwrap();
+    cfg.results_csv = Some(combined.to_str().unwrap().to_string());
+    cfg.run_tag = Some(String::from("merged"));
+
+    // shard 1 also ran combination 6, which is only counted once
+    let inputs = [fixture("shard-0-of-2.csv"), fixture("shard-1-of-2.csv")];
+    let best = merge_slices(&inputs, &cfg).unwrap().unwrap();
+
+    assert_eq!(best.profit, 1600.);
+    assert_eq!(best.evaluation.index, 7);
+    assert_eq!(best.constants, "x = 0.500\ny = 3");
+
+    let state = load_state(state_file.to_str().unwrap()).unwrap();
+    assert_eq!(state.max_profit, 1600.);
+    assert_eq!(state.constants, best.constants);
+
+    let contents = fs::read_to_string(&combined).unwrap();
+    let rows = contents.lines().collect::<Vec<_>>();
+    assert_eq!(rows[0], "tag,index,outcome,profit,duration_secs,x,y");
+    assert_eq!(rows.len(), 9);
+    assert_eq!(rows[4], "merged,3,no_profit,,1.4,0,3");
+    assert_eq!(rows[8], "merged,7,success,1600,1.3,0.5,3");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn csv_without_its_config_is_refused() {
+    let dir = std::env::temp_dir().join(format!("grid_search-merge-bare-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    // copied away from the config that was written next to it
+    let bare = dir.join("shard-0-of-2.csv");
+    fs::copy(fixture("shard-0-of-2.csv"), &bare).unwrap();
+
+    let cfg = ConfigBuilder::new()
+        .add_variable("x", 0., 1., 0.5)
+        .add_variable("y", 0., 4., 1.)
+        .build()
+        .unwrap();
+
+    let inputs = [
+        bare.to_str().unwrap().to_string(),
+        fixture("shard-1-of-2.csv"),
+    ];
+    let err = merge_slices(&inputs, &cfg).unwrap_err();
+    assert!(
+        err.to_string().contains("shard-0-of-2.config.json"),
+        "{}",
+        err
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
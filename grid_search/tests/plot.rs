@@ -0,0 +1,50 @@
+use std::collections::BTreeMap;
+
+use grid_search::{
+    config::ConfigBuilder,
+    evaluation::{Evaluation, RunOutcome},
+    plot::vega_lite_spec,
+};
+
+fn evaluation(index: usize, x: f64, y: f64) -> Evaluation {
+    Evaluation {
+        index,
+        parameters: BTreeMap::from([(String::from("x"), x), (String::from("y"), y)]),
+        profit: Some(x * 10. + y),
+        outcome: RunOutcome::Success,
+        raw_profit: None,
+        baseline_delta: None,
+        profit_std: None,
+        duration_secs: 0.,
+        error: None,
+    }
+}
+
+#[test]
+fn two_variables_make_a_heatmap() {
+    let cfg = ConfigBuilder::new()
+        .add_variable("x", 0., 2., 1.)
+        .add_variable("y", 0., 3., 1.)
+        .build()
+        .unwrap();
+
+    let evaluations = (0..6)
+        .map(|i| evaluation(i, (i / 3) as f64, (i % 3) as f64))
+        .collect::<Vec<_>>();
+
+    let spec = vega_lite_spec(&evaluations, &cfg.variables).unwrap();
+
+    // what's written to disk parses back as the same spec
+    let written = serde_json::to_string_pretty(&spec).unwrap();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&written).unwrap(),
+        spec
+    );
+
+    assert_eq!(spec["mark"], "rect");
+    assert_eq!(spec["encoding"]["x"]["field"], "x");
+    assert_eq!(spec["encoding"]["y"]["field"], "y");
+    assert_eq!(spec["encoding"]["color"]["field"], "profit");
+    assert_eq!(spec["data"]["values"].as_array().unwrap().len(), 6);
+    assert_eq!(spec["data"]["values"][5]["profit"], 12.);
+}
@@ -0,0 +1,50 @@
+use grid_search::{
+    config::{ConfigBuilder, ProfitMatch},
+    get_profit,
+};
+
+const CHATTY_OUTPUT: &str = concat!(
+    "Backtesting trader.py on round 1\n",
+    "Day 0: Total profit: 1,200 so far, target Total profit: 9,999\n",
+    "  Total profit: 3,456  \n",
+);
+
+#[test]
+fn strict_parse_ignores_the_pattern_part_way_through_a_line() {
+    let mut cfg = ConfigBuilder::new().build().unwrap();
+    assert_eq!(
+        get_profit(CHATTY_OUTPUT, &cfg.profit_regex(), cfg.profit_match),
+        Some(1200.)
+    );
+
+    cfg.strict_parse = true;
+    assert_eq!(
+        get_profit(CHATTY_OUTPUT, &cfg.profit_regex(), cfg.profit_match),
+        Some(3456.)
+    );
+}
+
+#[test]
+fn each_profit_match_mode_picks_its_line() {
+    // a profit per day, as some backtesters print
+    let output = concat!(
+        "Day -2: Total profit: 1,200\n",
+        "Day -1: Total profit: 3,500\n",
+        "Day 0: Total profit: 800\n",
+    );
+    let cfg = ConfigBuilder::new().build().unwrap();
+
+    for (profit_match, expected) in [
+        (ProfitMatch::First, 1200.),
+        (ProfitMatch::Last, 800.),
+        (ProfitMatch::Max, 3500.),
+        (ProfitMatch::Sum, 5500.),
+    ] {
+        assert_eq!(
+            get_profit(output, &cfg.profit_regex(), profit_match),
+            Some(expected),
+            "{:?}",
+            profit_match
+        );
+    }
+}
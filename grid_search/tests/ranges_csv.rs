@@ -0,0 +1,27 @@
+use std::path::Path;
+
+use grid_search::{
+    config::{ConfigBuilder, read_ranges_csv, validate_config},
+    get_constant_strings,
+};
+
+#[test]
+fn variables_load_from_a_csv() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/ranges.csv");
+
+    let mut cfg = ConfigBuilder::new().build().unwrap();
+    cfg.variables = read_ranges_csv(path.to_str().unwrap()).unwrap();
+    validate_config(&cfg).unwrap();
+
+    let names = cfg
+        .variables
+        .iter()
+        .map(|v| v.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["edge", "window"]);
+    assert_eq!(cfg.variables[0].step, Some(0.5));
+    assert_eq!(cfg.variables[1].end, 40.);
+
+    // 3 edges by 3 windows
+    assert_eq!(get_constant_strings(&cfg).len(), 9);
+}
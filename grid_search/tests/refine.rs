@@ -0,0 +1,44 @@
+use std::collections::BTreeMap;
+
+use grid_search::{
+    config::ConfigBuilder,
+    refine::{REFINE_FRACTION, refine_variables},
+    values_for,
+};
+
+fn assert_values(actual: Vec<f64>, expected: &[f64]) {
+    assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn refine_centres_narrowed_ranges_on_the_best() {
+    let cfg = ConfigBuilder::new()
+        .add_variable("x", 0., 1., 0.25)
+        .add_variable("y", 0., 4., 1.)
+        .build()
+        .unwrap();
+    let best = BTreeMap::from([(String::from("x"), 0.5), (String::from("y"), 0.)]);
+
+    let refined = refine_variables(&cfg.variables, &best, REFINE_FRACTION).unwrap();
+
+    // x had 4 values, so gets 5 spanning a tenth of its range either side
+    assert_values(values_for(&refined[0]), &[0.4, 0.45, 0.5, 0.55, 0.6]);
+
+    // y's best is at its start, so the range is clamped there
+    assert_values(values_for(&refined[1]), &[0., 0.1, 0.2, 0.3, 0.4]);
+}
+
+#[test]
+fn refine_needs_every_variable_in_the_best() {
+    let cfg = ConfigBuilder::new()
+        .add_variable("x", 0., 1., 0.25)
+        .build()
+        .unwrap();
+
+    let err = refine_variables(&cfg.variables, &BTreeMap::new(), REFINE_FRACTION).unwrap_err();
+    assert!(err.contains("no value for x"));
+}
@@ -0,0 +1,60 @@
+use grid_search::{
+    config::ConfigBuilder,
+    sampling::{latin_hypercube, new_rng},
+};
+
+// the largest gap between the share of samples in a box anchored at the
+// origin and that box's area, over a grid of boxes, for samples in the unit square
+fn star_discrepancy(samples: &[Vec<f64>]) -> f64 {
+    let n = samples.len() as f64;
+    let corners = (1..=50).map(|i| i as f64 / 50.).collect::<Vec<_>>();
+    let mut worst: f64 = 0.;
+
+    for &a in &corners {
+        for &b in &corners {
+            let inside = samples.iter().filter(|s| s[0] < a && s[1] < b).count() as f64;
+            worst = worst.max((inside / n - a * b).abs());
+        }
+    }
+
+    worst
+}
+
+#[test]
+fn every_stratum_is_sampled_once() {
+    let cfg = ConfigBuilder::new()
+        .add_variable("edge", 0., 1., 0.1)
+        .add_variable("width", 10., 30., 1.)
+        .build()
+        .unwrap();
+    let n = 40;
+
+    let samples = latin_hypercube(&cfg.variables, n, &mut new_rng(Some(405)));
+    assert_eq!(samples.len(), n);
+
+    for (i, var) in cfg.variables.iter().enumerate() {
+        let mut strata = samples
+            .iter()
+            .map(|s| ((s[i] - var.start) / (var.end - var.start) * n as f64) as usize)
+            .collect::<Vec<_>>();
+        strata.sort();
+
+        assert_eq!(strata, (0..n).collect::<Vec<_>>(), "{}", var.name);
+    }
+}
+
+#[test]
+fn samples_cover_the_space_evenly() {
+    let cfg = ConfigBuilder::new()
+        .add_variable("x", 0., 1., 0.1)
+        .add_variable("y", 0., 1., 0.1)
+        .build()
+        .unwrap();
+
+    for seed in 0..10 {
+        let samples = latin_hypercube(&cfg.variables, 100, &mut new_rng(Some(seed)));
+        let discrepancy = star_discrepancy(&samples);
+
+        assert!(discrepancy < 0.1, "seed {}: {}", seed, discrepancy);
+    }
+}
@@ -0,0 +1,88 @@
+use std::{collections::BTreeMap, fs};
+
+use grid_search::{
+    evaluation::{Evaluation, RunOutcome},
+    state::{State, load_state, save_state},
+};
+
+#[test]
+fn state_file_in_a_missing_directory_is_created() {
+    let dir = std::env::temp_dir().join(format!("grid_search-state-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let state_file = dir.join("results/best.json");
+
+    let state = State {
+        max_profit: 1500.,
+        constants: String::from("x = 0.500\ny = 2"),
+        best: None,
+        note: None,
+        script_path: None,
+        log_path: None,
+    };
+    save_state(state_file.to_str().unwrap(), &state).unwrap();
+
+    let loaded = load_state(state_file.to_str().unwrap()).unwrap();
+    assert_eq!(loaded.max_profit, 1500.);
+    assert_eq!(loaded.constants, state.constants);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn state_round_trips_through_its_file() {
+    let dir = std::env::temp_dir().join(format!("grid_search-state-trip-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let state_file = dir.join("best.json");
+
+    let state = State {
+        max_profit: 1400.,
+        constants: String::from("x = 0.5\ny = 1"),
+        best: Some(Evaluation {
+            index: 5,
+            parameters: BTreeMap::from([(String::from("x"), 0.5), (String::from("y"), 1.)]),
+            profit: Some(1400.),
+            outcome: RunOutcome::Success,
+            raw_profit: Some(1450.),
+            baseline_delta: None,
+            profit_std: Some(12.5),
+            duration_secs: 3.25,
+            error: None,
+        }),
+        note: Some(String::from("wider y")),
+        script_path: Some(String::from("out/0-99/scripts/script_5.py")),
+        log_path: Some(String::from("out/0-99/logs/log_5.txt")),
+    };
+    save_state(state_file.to_str().unwrap(), &state).unwrap();
+
+    let loaded = load_state(state_file.to_str().unwrap()).unwrap();
+    assert_eq!(
+        serde_json::to_value(&loaded).unwrap(),
+        serde_json::to_value(&state).unwrap()
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn state_from_grid_search_py_still_loads() {
+    let dir = std::env::temp_dir().join(format!("grid_search-state-old-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let state_file = dir.join("best.json");
+
+    // all the python version wrote
+    fs::write(
+        &state_file,
+        r#"{"max_profit": 1500.0, "constants": "x = 0.5\ny = 2"}"#,
+    )
+    .unwrap();
+
+    let loaded = load_state(state_file.to_str().unwrap()).unwrap();
+    assert_eq!(loaded.max_profit, 1500.);
+    assert_eq!(loaded.constants, "x = 0.5\ny = 2");
+    assert!(loaded.best.is_none());
+    assert!(loaded.note.is_none());
+    assert!(loaded.script_path.is_none() && loaded.log_path.is_none());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,62 @@
+use grid_search::{Template, replace_constants, template_constants};
+
+const SCRIPT: &str = concat!(
+    "import json\n",
+    "# start\n",
+    "x = 1\n",
+    "y = 2\n",
+    "# end\n",
+    "class Trader:\n",
+    "    pass\n",
+);
+
+#[test]
+fn filling_replaces_only_the_constants_block() {
+    let template = Template::split(SCRIPT, "# start", "# end").unwrap();
+
+    assert_eq!(
+        template.fill("x = 0.500\ny = 3"),
+        "import json\n# start\nx = 0.500\ny = 3\n# end\nclass Trader:\n    pass\n"
+    );
+}
+
+#[test]
+fn one_split_fills_every_combination() {
+    let template = Template::split(SCRIPT, "# start", "# end").unwrap();
+
+    for constants in ["x = 0\ny = 0", "x = 1\ny = 4", ""] {
+        assert_eq!(
+            template.fill(constants),
+            replace_constants(SCRIPT, constants, "# start", "# end").unwrap()
+        );
+    }
+}
+
+#[test]
+fn only_the_first_block_is_filled() {
+    let script = format!("{}# start\nz = 9\n# end\n", SCRIPT);
+    let template = Template::split(&script, "# start", "# end").unwrap();
+
+    let filled = template.fill("x = 5\ny = 5");
+    assert!(filled.starts_with("import json\n# start\nx = 5\ny = 5\n# end\n"));
+    assert!(filled.ends_with("# start\nz = 9\n# end\n"));
+    assert_eq!(
+        template_constants(&script, "# start", "# end").unwrap(),
+        "\nx = 1\ny = 2\n"
+    );
+}
+
+#[test]
+fn missing_or_misplaced_markers_are_named() {
+    let err = Template::split(SCRIPT, "# begin", "# end").err().unwrap();
+    assert!(err.contains("start marker \"# begin\""), "{}", err);
+
+    let err = Template::split(SCRIPT, "# start", "# stop").err().unwrap();
+    assert!(err.contains("end marker \"# stop\""), "{}", err);
+
+    let backwards = "# end\nx = 1\n# start\n";
+    let err = Template::split(backwards, "# start", "# end")
+        .err()
+        .unwrap();
+    assert!(err.contains("comes before the start marker"), "{}", err);
+}
@@ -0,0 +1,58 @@
+#![cfg(feature = "cli")]
+
+use std::{fs, sync::mpsc, thread, time::Duration};
+
+use grid_search::{
+    config::ConfigBuilder,
+    watch::{WatchEvent, watch_until},
+};
+
+#[test]
+fn a_saved_change_is_reported_before_running() {
+    let dir = std::env::temp_dir().join(format!("grid_search-watch-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let config_path = dir.join("config.json");
+
+    let mut cfg = ConfigBuilder::new()
+        .add_variable("x", 0., 1., 0.5)
+        .build()
+        .unwrap();
+    fs::write(&config_path, serde_json::to_string(&cfg).unwrap()).unwrap();
+
+    // the file is edited, and enter pressed once the edit has been picked up
+    let (tx, rx) = mpsc::channel();
+    let editor = {
+        let tx = tx.clone();
+        let config_path = config_path.clone();
+        cfg.variables[0].end = 2.;
+
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(300));
+            fs::write(&config_path, serde_json::to_string(&cfg).unwrap()).unwrap();
+
+            thread::sleep(Duration::from_millis(1000));
+            tx.send(WatchEvent::Confirmed).unwrap();
+        })
+    };
+
+    let mut out = Vec::new();
+    let watched = watch_until(config_path.to_str().unwrap(), tx, rx, &mut out).unwrap();
+    editor.join().unwrap();
+
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with("2 combinations"), "{}", out);
+    assert!(
+        out.contains("Config changed:\n  ~ variable x.end: 1.0 -> 2.0\n"),
+        "{}",
+        out
+    );
+    assert!(
+        out.ends_with("4 combinations, press enter to run or keep editing\n"),
+        "{}",
+        out
+    );
+    assert_eq!(watched.variables[0].end, 2.);
+
+    fs::remove_dir_all(&dir).unwrap();
+}